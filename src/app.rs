@@ -1,16 +1,20 @@
 use std::fmt::Debug;
 use glam::IVec3;
-use log::warn;
-use winit::window::Window;
+use log::{error, warn};
+use winit::window::{CursorGrabMode, Window};
 use winit::event::{DeviceEvent, Event, WindowEvent};
 use winit::event_loop::{EventLoop, ControlFlow, EventLoopProxy};
 use winit::dpi::PhysicalSize;
+use crate::game::client::chat::Chat;
 use crate::game::client::client::Client;
 use crate::game::client::graphics::chunk_model::ChunkModel;
-use crate::game::client::graphics::world_renderer::WorldRenderer;
-use crate::game::network::packet::{ClientPacket, ClientJoinClientPacket, ServerPacket};
-use crate::game::client::window::WindowStack;
+use crate::game::network::chunk_encoding::ChunkEncoding;
+use crate::game::network::keepalive::Keepalive;
+use crate::game::network::packet::{ClientPacket, ClientJoinClientPacket, ServerPacket, HandshakeClientPacket, PROTOCOL_VERSION, BUILD_ID};
+use crate::game::client::window::{WindowId, WindowStack};
 use crate::game::client::window::server_join::ServerJoinWindow;
+use crate::game::client::window::hud::HudWindow;
+use crate::game::client::window::chat::ChatWindow;
 use crate::game::world::chunk::CHUNK_SIZE;
 use crate::graphics::context::Graphics;
 use crate::graphics::egui::EGuiContext;
@@ -46,7 +50,12 @@ pub struct App {
   pub input       : Input,
   pub graphics    : Graphics,
   pub egui_ctx    : EGuiContext,
-  pub connection  : Option<Connection>,
+
+  pub connection        : Option<Connection>,
+  pub disconnect_reason : Option<String>, // shown in the join window after the server closed the connection
+  pub handshake_sent    : bool, // nothing else may be sent before the handshake, the server would refuse it
+  pub keepalive         : Keepalive,
+  pub chat              : Chat,
 
   pub last_update : instant::Instant,
   pub last_render : instant::Instant,
@@ -61,7 +70,9 @@ impl App {
     let event_proxy = event_loop.create_proxy();
 
     let mut window_stack: WindowStack = vec![
-      Box::<ServerJoinWindow>::default()
+      Box::<HudWindow>::default(),
+      Box::<ChatWindow>::default(),
+      Box::<ServerJoinWindow>::default(),
     ];
 
     let now = instant::Instant::now();
@@ -73,20 +84,18 @@ impl App {
 
       egui_ctx,
 
-      connection : None,
+      connection        : None,
+      disconnect_reason : None,
+      handshake_sent    : false,
+      keepalive         : Keepalive::new(now),
+      chat              : Chat::default(),
 
       last_update : now,
       last_render : now,
       delta       : instant::Duration::ZERO,
     };
 
-    let mut client = {
-      let world_renderer = WorldRenderer::new(&app);
-
-      Client::new(
-        &mut app,
-      )
-    };
+    let mut client = Client::new(&mut app);
 
     event_loop.run(move |event, _, control_flow| {
       *control_flow = ControlFlow::Poll;
@@ -151,8 +160,17 @@ impl App {
           match event {
             UVxlEvent::ConnectionReady => {
               dbg!(&client.player.name);
+              app.keepalive = Keepalive::default();
+              app.handshake_sent = true;
+
+              app.connection.as_mut().unwrap().send(ClientPacket::HandshakeClientPacket(HandshakeClientPacket {
+                protocol_version : PROTOCOL_VERSION,
+                build            : BUILD_ID.to_string(),
+              })).unwrap();
+
               app.connection.as_mut().unwrap().send(ClientPacket::ClientJoinClientPacket(ClientJoinClientPacket {
-                name: client.player.name.clone(),
+                name            : client.player.name.clone(),
+                chunk_encodings : ChunkEncoding::ALL.to_vec(),
               })).unwrap();
             }
 
@@ -161,12 +179,16 @@ impl App {
             }
 
             UVxlEvent::IncomingPacket(packet) => {
+              app.keepalive.received(instant::Instant::now());
               client.packet(&mut app, &packet);
             }
 
             UVxlEvent::MesherChunkDone(position, data) => {
-              let chunk_mesh = InstancedMesh::new(&app.graphics, data, vec![ChunkModel { position: (position * CHUNK_SIZE as i32).as_vec3() }]);
-              client.world_renderer.chunk_renderer.chunk_meshes.insert(position, chunk_mesh);
+              // the chunk may have been unloaded while it was being meshed
+              if client.world.chunk_manager.chunks.contains_key(&position) {
+                let chunk_mesh = InstancedMesh::new(&app.graphics, data, vec![ChunkModel { position: (position * CHUNK_SIZE as i32).as_vec3() }]);
+                client.world_renderer.chunk_renderer.chunk_meshes.insert(position, chunk_mesh);
+              }
             }
 
             UVxlEvent::SetClientName(name) => {
@@ -183,6 +205,22 @@ impl App {
 }
 
 impl App {
+  /// Drops the connection and goes back to the join window, which shows the reason.
+  pub fn disconnect(&mut self, reason: String) {
+    self.connection = None;
+    self.handshake_sent = false;
+    self.disconnect_reason = Some(reason);
+    self.chat.open = false;
+    self.window.set_cursor_grab(CursorGrabMode::None)
+      .unwrap_or_else(|err| error!("Failed to release mouse cursor: {}", err));
+
+    if let Err(err) = self.event_proxy.send_event(UVxlEvent::MutateWindowStack(Box::new(|_, stack| {
+      if !stack.iter().any(|window| window.id() == WindowId::ServerJoin) {
+        stack.push(Box::<ServerJoinWindow>::default());
+      }
+    }))) { error!("Failed to send UVxl event: {}", err); }
+  }
+
   fn render(&mut self, client: &mut Client, window_stack: &mut WindowStack) -> Result<(), wgpu::SurfaceError> {
     let output = self.graphics.surface.get_current_texture()?;
     let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
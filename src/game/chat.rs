@@ -0,0 +1,42 @@
+use std::fmt::{Display, Formatter};
+
+/// Longest chat message in characters, unless the server is configured otherwise.
+pub const DEFAULT_MAX_CHAT_LENGTH: usize = 256;
+
+/// Why a chat message wasn't sent, shown to the player who wrote it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChatError {
+  /// Nothing is left after removing whitespace and control characters.
+  Empty,
+  /// The message has more characters than the server allows.
+  TooLong { length: usize, max: usize },
+  /// The player sent too many messages recently.
+  RateLimited,
+}
+
+impl Display for ChatError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      ChatError::Empty => f.write_str("Messages can't be empty"),
+      ChatError::TooLong { length, max } => write!(f, "Messages can't be longer than {} characters, yours has {}", max, length),
+      ChatError::RateLimited => f.write_str("You are sending messages too quickly"),
+    };
+  }
+}
+
+/// Removes control characters and surrounding whitespace, so messages can't mess with the chat layout.
+pub fn sanitize(message: &str, max_length: usize) -> Result<String, ChatError> {
+  let message = message.chars().filter(|x| !x.is_control()).collect::<String>();
+  let message = message.trim();
+
+  let length = message.chars().count();
+  if length == 0 {
+    return Err(ChatError::Empty);
+  }
+
+  if length > max_length {
+    return Err(ChatError::TooLong { length, max: max_length });
+  }
+
+  return Ok(message.to_string());
+}
\ No newline at end of file
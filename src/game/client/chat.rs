@@ -0,0 +1,71 @@
+use std::collections::VecDeque;
+use instant::{Duration, Instant};
+
+// Older messages are dropped from the scrollback
+const MAX_LINES: usize = 100;
+
+/// How long new messages stay on screen while the chat is closed.
+pub const MESSAGE_LIFETIME: Duration = Duration::from_secs(10);
+
+pub struct ChatLine {
+  pub sender   : Option<String>, // none for messages of the server
+  pub message  : String,
+  pub received : Instant,
+}
+
+/// Suggestions of the server for the last word of a command typed into the chat.
+#[derive(Debug)]
+pub struct Completion {
+  pub input       : String, // as it was when the completion was requested
+  pub start       : usize, // byte offset of the word the suggestions replace
+  pub suggestions : Vec<String>,
+}
+
+impl Completion {
+  /// The input with its last word completed, as far as the suggestions agree. A single suggestion is
+  /// taken as a whole, followed by a space. Returns `None` if the input changed in the meantime.
+  pub fn apply(&self, input: &str) -> Option<String> {
+    if input != self.input || !input.is_char_boundary(self.start) {
+      return None;
+    }
+
+    let completed = match self.suggestions.as_slice() {
+      [] => return None,
+      [suggestion] => format!("{} ", suggestion),
+      [first, rest @ ..] => {
+        let length = rest.iter()
+          .map(|x| first.chars().zip(x.chars()).take_while(|(a, b)| a == b).map(|(a, _)| a.len_utf8()).sum())
+          .min()
+          .unwrap_or(first.len());
+
+        first[.. length].to_string()
+      }
+    };
+
+    return Some(format!("{}{}", &input[.. self.start], completed));
+  }
+}
+
+/// Messages received from the server, shown by the chat window.
+#[derive(Default)]
+pub struct Chat {
+  pub lines      : VecDeque<ChatLine>,
+  pub open       : bool, // the player is typing, the game doesn't react to input meanwhile
+  pub completion : Option<Completion>, // received but not yet applied to the input
+}
+
+impl Chat {
+  pub fn push(&mut self, sender: Option<String>, message: String) {
+    if self.lines.len() == MAX_LINES {
+      self.lines.pop_front();
+    }
+
+    self.lines.push_back(ChatLine { sender, message, received: Instant::now() });
+  }
+
+  /// Messages which arrived within `MESSAGE_LIFETIME`, oldest first.
+  pub fn recent(&self) -> impl Iterator<Item = &ChatLine> {
+    let start = self.lines.iter().position(|x| x.received.elapsed() < MESSAGE_LIFETIME).unwrap_or(self.lines.len());
+    return self.lines.range(start ..);
+  }
+}
\ No newline at end of file
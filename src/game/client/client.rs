@@ -1,17 +1,27 @@
-use glam::{ivec3, Quat, vec3};
-use log::error;
+use std::collections::HashMap;
+use glam::{IVec3, ivec3, vec3};
+use log::{debug, error};
+use uuid::Uuid;
 use winit::dpi::PhysicalSize;
-use winit::event::{DeviceEvent, WindowEvent};
+use winit::event::{DeviceEvent, ElementState, MouseButton, VirtualKeyCode, WindowEvent};
 use crate::app::{App, UVxlEvent};
+use crate::game::client::chat::Completion;
 use crate::game::client::graphics::entity_model::EntityModel;
 use crate::game::client::graphics::world_renderer::WorldRenderer;
 use crate::game::client::window::WindowId;
 use crate::game::entity::{Entity, EntityState};
+use crate::game::entity::interpolation::{SnapshotBuffer, INTERPOLATION_DELAY};
+use crate::game::entity::orientation::Orientation;
+use crate::game::entity::prediction::MovementPrediction;
 use crate::game::entity::player::EntityPlayer;
-use crate::game::network::packet::{InitialChunkDataServerPacket, ClientJoinSuccessServerPacket, ServerPacket, ClientPacket, ClientMovePacket, PlayerJoinServerPacket, PlayerMoveServerPacket, ErrorServerPacket};
-use crate::game::player::Player;
-use crate::game::world::chunk::ChunkVec3Ext;
+use crate::game::network::packet::{BlockBreakClientPacket, BlockPlaceClientPacket, BlockUpdateServerPacket, ChunkUnloadServerPacket, InitialChunkDataServerPacket, ClientJoinSuccessServerPacket, ServerPacket, ClientPacket, ClientMovePacket, PlayerSpawnServerPacket, PlayerMoveServerPacket, PlayerDespawnServerPacket, ErrorServerPacket, PingPacket, MoveAckServerPacket, ChatServerPacket, CompletionsServerPacket};
+use crate::game::network::keepalive::{DEFAULT_IDLE_TIMEOUT, DEFAULT_KEEPALIVE_INTERVAL};
+use crate::game::player::{Player, PLAYER_REACH, PLAYER_SPEED};
+use crate::game::world::BlockId;
+use crate::game::world::chunk::{ChunkIVec3Ext, ChunkVec3Ext, CHUNK_SIZE};
+use crate::game::world::raycast::{path_blocked, raycast, RaycastHit};
 use crate::game::world::world::World;
+use crate::graphics::instance::Instance;
 use crate::input::camera_controller::CameraController;
 
 pub struct Client {
@@ -21,36 +31,99 @@ pub struct Client {
 
   pub world  : World,
   pub player : Player, // later we might want to have a client player which holds addition client information such as auth or other stuff
+
+  pub player_snapshots : HashMap<Uuid, SnapshotBuffer>, // positions of other players as they were received
+  pub prediction       : MovementPrediction, // moves of the player the server hasn't acknowledged yet
+
+  pub selected_block : BlockId,
 }
 
 impl Client {
   pub fn new(app: &mut App) -> Self {
-    let world_renderer = WorldRenderer::new(app);
-    let camera_controller = CameraController::new(20.0, 1.0);
+    let world = World::default();
+    let world_renderer = WorldRenderer::new(app, &world.registry);
+    let camera_controller = CameraController::new(PLAYER_SPEED, 1.0);
 
     return Self {
       world_renderer,
       camera_controller,
 
-      world: Default::default(),
+      world,
       player: Default::default(),
+
+      player_snapshots: HashMap::new(),
+      prediction: MovementPrediction::new(vec3(0.0, 0.0, 0.0)),
+
+      selected_block: BlockId::AIR,
     };
   }
 
   pub fn render(&mut self, app: &mut App, view: &wgpu::TextureView, encoder: &mut wgpu::CommandEncoder) {
-    self.camera_controller.update_camera(&mut self.world_renderer.scene.camera, app.delta);
+    let camera = &mut self.world_renderer.scene.camera;
+    let previous = camera.position;
+    self.camera_controller.update_camera(camera, app.delta);
+
+    // the server refuses moves through solid blocks, move along each axis which isn't blocked instead of being corrected
+    let target = std::mem::replace(&mut camera.position, previous);
+    for axis in 0 .. 3 {
+      let mut next = camera.position;
+      next[axis] = target[axis];
+      if path_blocked(camera.position, next, |x| self.world.is_solid(x)).is_none() {
+        camera.position = next;
+      }
+    }
+
     self.player.entity.state_mut().position = self.world_renderer.scene.camera.position;
+    self.interpolate_players(app);
     self.world_renderer.render(app, view, encoder);
   }
 
   pub fn update(&mut self, app: &mut App) {
+    // the connection may still be opening, the wasm build opens it asynchronously
+    if !app.handshake_sent {
+      return;
+    }
+
+    let now = instant::Instant::now();
+    if app.connection.is_some() && app.keepalive.timed_out(now, DEFAULT_IDLE_TIMEOUT) {
+      error!("Connection timed out");
+      self.disconnect(app, String::from("Connection timed out"));
+      return;
+    }
+
     if let Some(connection) = &mut app.connection {
-      connection.send(ClientPacket::ClientMovePacket(ClientMovePacket {
-        position: self.player.entity.state().position,
-      })).unwrap();
+      if app.keepalive.ping_due(now, DEFAULT_KEEPALIVE_INTERVAL) {
+        let payload = app.keepalive.ping(now);
+        connection.send(ClientPacket::PingClientPacket(PingPacket { payload }))
+          .unwrap_or_else(|err| error!("Failed to send ping: {}", err));
+      }
+
+      // moves are applied right away and sent once the server placed the player
+      if !self.player.uuid.is_nil() {
+        let camera = &self.world_renderer.scene.camera;
+        let (sequence, movement) = self.prediction.record(camera.position);
+        connection.send(ClientPacket::ClientMovePacket(ClientMovePacket {
+          sequence,
+          movement,
+          orientation: Orientation::from_angles(camera.yaw, camera.pitch),
+        })).unwrap();
+      }
     }
   }
 
+  /// Forgets everything the server sent, so a new connection starts over, and goes back to the join window.
+  pub fn disconnect(&mut self, app: &mut App, reason: String) {
+    self.player.uuid = Uuid::nil();
+    self.prediction = MovementPrediction::new(self.player.entity.state().position);
+    self.world.players.clear();
+    self.player_snapshots.clear();
+    self.world.chunk_manager.chunks.clear();
+    self.world_renderer.chunk_renderer.chunk_meshes.clear();
+    self.update_player_instances(app);
+
+    app.disconnect(reason);
+  }
+
   pub fn resize(&mut self, app: &mut App, size: PhysicalSize<u32>) {
     self.world_renderer.resize(app, size);
   }
@@ -59,7 +132,38 @@ impl Client {
     match event {
       WindowEvent::KeyboardInput { input, .. } => {
         if let Some(keycode) = input.virtual_keycode {
+          // keys released while typing still stop the movement they started
+          if app.chat.open && input.state == ElementState::Pressed {
+            return;
+          }
+
           self.camera_controller.on_keyboard(keycode, input.state);
+
+          if input.state == ElementState::Pressed {
+            self.select_block(keycode);
+          }
+        }
+      }
+
+      WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } if !app.chat.open => {
+        let Some(connection) = &mut app.connection else { return };
+        let Some(hit) = self.target_block() else { return };
+
+        let packet = match button {
+          MouseButton::Left => ClientPacket::BlockBreakClientPacket(BlockBreakClientPacket {
+            position: hit.position,
+          }),
+
+          MouseButton::Right if hit.normal != IVec3::ZERO && self.selected_block != BlockId::AIR => ClientPacket::BlockPlaceClientPacket(BlockPlaceClientPacket {
+            position: hit.position + hit.normal,
+            block: self.selected_block,
+          }),
+
+          _ => return,
+        };
+
+        if let Err(err) = connection.send(packet) {
+          error!("Failed to send packet: {}", err);
         }
       }
 
@@ -69,7 +173,7 @@ impl Client {
 
   pub fn device_event(&mut self, app: &mut App, event: &DeviceEvent) {
     match event {
-      DeviceEvent::MouseMotion { delta } => {
+      DeviceEvent::MouseMotion { delta } if !app.chat.open => {
         self.camera_controller.on_mouse(delta.0, delta.1);
       }
 
@@ -77,35 +181,105 @@ impl Client {
     }
   }
 
+  // Block the player is looking at
+  fn target_block(&self) -> Option<RaycastHit> {
+    let camera = &self.world_renderer.scene.camera;
+    return raycast(camera.position, camera.direction(), PLAYER_REACH, |position| {
+      self.world.chunk_manager.get_block(position).is_some_and(|block| block != BlockId::AIR)
+    });
+  }
+
+  // Number keys select one of the first nine placeable blocks
+  fn select_block(&mut self, keycode: VirtualKeyCode) {
+    let index = match keycode {
+      VirtualKeyCode::Key1 => 0, VirtualKeyCode::Key2 => 1, VirtualKeyCode::Key3 => 2,
+      VirtualKeyCode::Key4 => 3, VirtualKeyCode::Key5 => 4, VirtualKeyCode::Key6 => 5,
+      VirtualKeyCode::Key7 => 6, VirtualKeyCode::Key8 => 7, VirtualKeyCode::Key9 => 8,
+      _ => return,
+    };
+
+    if let Some((block, _)) = self.world.registry.iter().filter(|(_, x)| x.textures.is_some()).nth(index) {
+      self.selected_block = block;
+    }
+  }
+
+  // Rebuilds the instances of other players after they were spawned or despawned
+  fn update_player_instances(&mut self, app: &App) {
+    self.world_renderer.entity_renderer.entities_mesh.instances = self.world.players.iter()
+      .map(|x| EntityModel { position: x.entity.state().position, rotation: x.entity.state().rotation }).collect();
+    self.world_renderer.entity_renderer.entities_mesh.bake_instances(&app.graphics);
+  }
+
+  // Moves other players to where they were a moment ago, between the positions received around that time
+  fn interpolate_players(&mut self, app: &App) {
+    if self.world.players.is_empty() {
+      return;
+    }
+
+    let Some(time) = instant::Instant::now().checked_sub(INTERPOLATION_DELAY) else { return };
+    for player in &mut self.world.players {
+      if let Some((position, rotation)) = self.player_snapshots.get(&player.uuid).and_then(|x| x.sample(time)) {
+        let state = player.entity.state_mut();
+        state.position = position;
+        state.rotation = rotation;
+      }
+    }
+
+    // the number of players only changes along with the instance buffer, so it can be written in place
+    let mesh = &mut self.world_renderer.entity_renderer.entities_mesh;
+    for (instance, player) in mesh.instances.iter_mut().zip(&self.world.players) {
+      instance.position = player.entity.state().position;
+      instance.rotation = player.entity.state().rotation;
+    }
+
+    let baked = mesh.instances.iter().map(Instance::bake).collect::<Vec<_>>();
+    mesh.update_instances(&baked, &app.graphics.queue);
+  }
+
+  fn remesh_chunk(&self, chunk_pos: IVec3) {
+    let Some(chunk) = self.world.chunk_manager.chunks.get(&chunk_pos) else { return };
+    if let Err(err) = self.world_renderer.chunk_renderer.chunk_sender.send((chunk_pos, chunk.clone())) {
+      error!("Failed to send chunk to the mesher: {}", err);
+    }
+  }
+
   pub fn packet(&mut self, app: &mut App, packet: &ServerPacket) {
     match packet {
-      ServerPacket::ClientJoinSuccessServerPacket(ClientJoinSuccessServerPacket { uuid, position, players }) => {
+      ServerPacket::ClientJoinSuccessServerPacket(ClientJoinSuccessServerPacket { uuid, position, blocks }) => {
         if let Err(err) = app.event_proxy.send_event(UVxlEvent::MutateWindowStack(Box::new(move |app, stack| {
           stack.retain(|window| window.id() != WindowId::ServerJoin);
         }))) { error!("Failed to send UVxl event: {}", err); }
 
         self.player.uuid = *uuid;
+        self.player.entity.state_mut().position = *position;
+        self.world_renderer.scene.camera.position = *position;
+        self.prediction = MovementPrediction::new(*position);
 
-        for player in players {
-          let entity = EntityPlayer::new(
-            EntityState {
-              title: Some(player.name.clone()),
-              position: player.position,
-              velocity: vec3(0.0, 0.0, 0.0),
-              rotation: Quat::IDENTITY,
-            }
-          );
+        self.world.registry = blocks.clone();
+        self.world_renderer.chunk_renderer.set_registry(&self.world.registry);
+        self.selected_block = self.world.registry.iter()
+          .find(|(_, x)| x.textures.is_some())
+          .map(|(block, _)| block)
+          .unwrap_or(BlockId::AIR);
+      }
 
-          self.world.players.push(Player { uuid: player.uuid, name: player.name.clone(), entity });
+      ServerPacket::InitialChunkDataServerPacket(InitialChunkDataServerPacket { chunk, position }) => {
+        let chunk = match chunk.decode() {
+          Ok(chunk) => chunk,
+          Err(err) => {
+            error!("Failed to decode chunk {}: {:?}", position, err);
+            return;
+          }
+        };
 
-          self.world_renderer.entity_renderer.entities_mesh.instances = self.world.players.iter()
-            .map(|x| EntityModel { position: x.entity.state().position }).collect();
-          self.world_renderer.entity_renderer.entities_mesh.bake_instances(&app.graphics);
+        // chunks are resent when they changed as a whole, e.g. filled by a command, their meshes are stale
+        if self.world.chunk_manager.chunks.insert(*position, chunk).is_some() {
+          self.remesh_chunk(*position);
+          for offset in [IVec3::X, IVec3::NEG_X, IVec3::Y, IVec3::NEG_Y, IVec3::Z, IVec3::NEG_Z] {
+            self.remesh_chunk(*position + offset);
+          }
         }
-      }
 
-      ServerPacket::InitialChunkDataServerPacket(InitialChunkDataServerPacket { chunk, position }) => {
-        self.world.chunk_manager.chunks.insert(*position, chunk.clone());
         // self.world_renderer.chunk_renderer.chunk_meshes.clear();
 
         let vertical_render_distance = 4;
@@ -117,7 +291,7 @@ impl Client {
               let chunk_pos = ivec3(chunk_pos.x + x, chunk_pos.y + y, chunk_pos.z + z);
               let Some(chunk) = self.world.chunk_manager.chunks.get(&chunk_pos) else { continue };
               if !self.world_renderer.chunk_renderer.chunk_meshes.contains_key(&chunk_pos) {
-                self.world_renderer.chunk_renderer.chunk_sender.send((chunk_pos, chunk.blocks.clone())).unwrap();
+                self.world_renderer.chunk_renderer.chunk_sender.send((chunk_pos, chunk.clone())).unwrap();
               }
 
               // self.world_renderer.chunk_renderer.add_chunk(chunk_pos, chunk.clone(), &app.graphics);
@@ -126,7 +300,7 @@ impl Client {
         }
       }
 
-      ServerPacket::PlayerJoinServerPacket(PlayerJoinServerPacket { uuid, name, position }) => {
+      ServerPacket::PlayerSpawnServerPacket(PlayerSpawnServerPacket { uuid, name, position, orientation }) => {
         dbg!(&name);
 
         let entity = EntityPlayer::new(
@@ -134,31 +308,96 @@ impl Client {
             title: Some(name.clone()),
             position: *position,
             velocity: vec3(0.0, 0.0, 0.0),
-            rotation: Quat::IDENTITY,
+            rotation: orientation.rotation(),
           }
         );
 
+        self.world.players.retain(|x| x.uuid != *uuid);
         self.world.players.push(Player { uuid: *uuid, name: name.clone(), entity });
 
-        self.world_renderer.entity_renderer.entities_mesh.instances = self.world.players.iter()
-          .map(|x| EntityModel { position: x.entity.state().position }).collect();
-        self.world_renderer.entity_renderer.entities_mesh.bake_instances(&app.graphics);
+        let mut snapshots = SnapshotBuffer::default();
+        snapshots.push(instant::Instant::now(), *position, orientation.rotation());
+        self.player_snapshots.insert(*uuid, snapshots);
+
+        self.update_player_instances(app);
       }
 
-      ServerPacket::PlayerMoveServerPacket(PlayerMoveServerPacket { uuid, position }) => {
-        for player in &mut self.world.players {
-          if player.uuid == *uuid {
-            player.entity.state_mut().position = *position;
-          }
+      // shown once the render time catches up with it
+      ServerPacket::PlayerMoveServerPacket(PlayerMoveServerPacket { uuid, position, orientation }) => {
+        if let Some(snapshots) = self.player_snapshots.get_mut(uuid) {
+          snapshots.push(instant::Instant::now(), *position, orientation.rotation());
+        }
+      }
+
+      ServerPacket::PlayerDespawnServerPacket(PlayerDespawnServerPacket { uuid }) => {
+        self.world.players.retain(|x| x.uuid != *uuid);
+        self.player_snapshots.remove(uuid);
+        self.update_player_instances(app);
+      }
+
+      ServerPacket::BlockUpdateServerPacket(BlockUpdateServerPacket { position, block }) => {
+        if !self.world.chunk_manager.set_block(*position, *block) {
+          return;
+        }
+
+        let chunk_pos = position.to_chunk_pos();
+        self.remesh_chunk(chunk_pos);
+
+        // faces of neighbouring chunks may have been hidden or revealed
+        let local_pos = position.to_local_pos();
+        for axis in 0 .. 3 {
+          let mut offset = IVec3::ZERO;
+          if local_pos[axis] == 0 { offset[axis] = -1; }
+          else if local_pos[axis] == CHUNK_SIZE as i32 - 1 { offset[axis] = 1; }
+          else { continue; }
+
+          self.remesh_chunk(chunk_pos + offset);
         }
+      }
+
+      ServerPacket::ChunkUnloadServerPacket(ChunkUnloadServerPacket { position }) => {
+        self.world.chunk_manager.chunks.remove(position);
+        self.world_renderer.chunk_renderer.remove_chunk(*position);
+      }
 
-        self.world_renderer.entity_renderer.entities_mesh.instances = self.world.players.iter()
-          .map(|x| EntityModel { position: x.entity.state().position }).collect();
-        self.world_renderer.entity_renderer.entities_mesh.bake_instances(&app.graphics);
+      ServerPacket::ChatServerPacket(ChatServerPacket { sender, message }) => {
+        app.chat.push(sender.clone(), message.clone());
+      }
+
+      ServerPacket::CompletionsServerPacket(CompletionsServerPacket { input, start, suggestions }) => {
+        app.chat.completion = Some(Completion {
+          input       : input.clone(),
+          start       : *start,
+          suggestions : suggestions.clone(),
+        });
       }
 
       ServerPacket::ErrorServerPacket(ErrorServerPacket { error }) => {
         error!("Server error: {:?}", error);
+
+        // the server closes the connection after an error
+        self.disconnect(app, error.to_string());
+      }
+
+      ServerPacket::PingServerPacket(PingPacket { payload }) => {
+        let Some(connection) = &mut app.connection else { return };
+        connection.send(ClientPacket::PongClientPacket(PingPacket { payload: *payload }))
+          .unwrap_or_else(|err| error!("Failed to answer ping: {}", err));
+      }
+
+      ServerPacket::PongServerPacket(PingPacket { payload }) => {
+        app.keepalive.pong(instant::Instant::now(), *payload);
+      }
+
+      // a correction moves the player by however much the server disagrees, keeping the moves made since
+      ServerPacket::MoveAckServerPacket(MoveAckServerPacket { sequence, position }) |
+      ServerPacket::MoveCorrectionServerPacket(MoveAckServerPacket { sequence, position }) => {
+        let camera = &mut self.world_renderer.scene.camera;
+        if let Some(corrected) = self.prediction.acknowledge(*sequence, *position, camera.position) {
+          debug!("Server corrected the position from {} to {}", camera.position, corrected);
+          camera.position = corrected;
+          self.player.entity.state_mut().position = corrected;
+        }
       }
     }
   }
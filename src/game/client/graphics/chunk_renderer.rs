@@ -1,9 +1,13 @@
 use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use std::sync::mpsc::{channel, Sender};
 use glam::{IVec3, Vec2, vec3, Vec4};
+use log::error;
 use wgpu::RenderPass;
 use crate::game::client::graphics::chunk_model::ChunkModel;
+use crate::game::client::graphics::textures::texture_key;
 use crate::game::world::BlockId;
+use crate::game::world::block_registry::BlockRegistry;
 use crate::game::world::chunk::Chunk;
 use crate::graphics::atlas::Atlas;
 use crate::graphics::drawable::Drawable;
@@ -16,26 +20,60 @@ use crate::util::side::Side;
 
 pub type ChunkMesh = InstancedMesh<Vertex, ChunkModel>;
 
+/// Per-block data used by the mesher, resolved from the block registry and the texture atlas.
+#[derive(Default)]
+pub struct BlockMeshTable {
+  opaque : Vec<bool>,
+  faces  : Vec<Option<[Vec4; 6]>>, // indexed by `Side`
+}
+
+impl BlockMeshTable {
+  pub fn new(registry: &BlockRegistry, atlas: &Atlas<&'static str>) -> Self {
+    let opaque = registry.iter().map(|(_, block)| block.opaque).collect();
+    let faces = registry.iter()
+      .map(|(_, block)| block.textures.as_ref().map(|textures| {
+        Side::ALL.map(|side| atlas.uv(&texture_key(textures.get(side))))
+      }))
+      .collect();
+
+    return Self { opaque, faces };
+  }
+
+  pub fn is_opaque(&self, block: BlockId) -> bool {
+    return self.opaque.get(block.index()).copied().unwrap_or(false);
+  }
+
+  pub fn faces(&self, block: BlockId) -> Option<&[Vec4; 6]> {
+    return self.faces.get(block.index()).and_then(|x| x.as_ref());
+  }
+}
+
 pub struct ChunkRenderer {
-  pub atlas        : &'static Atlas<BlockId>,
+  pub atlas        : &'static Atlas<&'static str>,
+  pub block_table  : Arc<RwLock<BlockMeshTable>>,
   pub chunk_meshes : HashMap<IVec3, ChunkMesh>,
-  pub chunk_sender : Sender<(IVec3, Vec<BlockId>)>,
+  pub chunk_sender : Sender<(IVec3, Chunk)>,
 }
 
 impl ChunkRenderer {
-  pub fn new(atlas: Atlas<BlockId>, sender: impl Fn(IVec3, Vec<Vertex>) + Send + 'static) -> Self {
-    let (chunk_sender, receiver) = channel::<(IVec3, Vec<BlockId>)>();
+  pub fn new(atlas: Atlas<&'static str>, registry: &BlockRegistry, sender: impl Fn(IVec3, Vec<Vertex>) + Send + 'static) -> Self {
+    let (chunk_sender, receiver) = channel::<(IVec3, Chunk)>();
 
     let atlas: &_ = Box::leak(Box::new(atlas));
+    let block_table = Arc::new(RwLock::new(BlockMeshTable::new(registry, atlas)));
+
+    let mesher_block_table = block_table.clone();
     std::thread::spawn(move || {
       while let Ok((position, chunk)) = receiver.recv() {
-        let vertices = mesher::culled::<CHUNK_SIZE>(&chunk, atlas);
+        let Ok(block_table) = mesher_block_table.read() else { break };
+        let vertices = mesher::culled(&chunk, &block_table);
         sender(position, vertices);
       }
     });
 
     return Self {
       atlas,
+      block_table,
       chunk_meshes: HashMap::new(),
       chunk_sender,
     };
@@ -48,8 +86,17 @@ impl ChunkRenderer {
     }
   }
 
+  /// Rebuilds the mesher lookup table, must be called whenever the block id mapping changes.
+  pub fn set_registry(&mut self, registry: &BlockRegistry) {
+    match self.block_table.write() {
+      Ok(mut block_table) => *block_table = BlockMeshTable::new(registry, self.atlas),
+      Err(err) => error!("Failed to update block mesh table: {}", err),
+    }
+  }
+
   pub fn add_chunk(&mut self, chunk_pos: IVec3, chunk: Chunk, graphics: &Graphics) {
-    let vertices = mesher::culled::<CHUNK_SIZE>(&chunk.blocks, self.atlas);
+    let Ok(block_table) = self.block_table.read() else { return };
+    let vertices = mesher::culled(&chunk, &block_table);
     let chunk_mesh = InstancedMesh::new(graphics, vertices, vec![ChunkModel { position: (chunk_pos * CHUNK_SIZE as i32).as_vec3() }]);
     self.chunk_meshes.insert(chunk_pos, chunk_mesh);
   }
@@ -65,20 +112,20 @@ pub mod mesher {
 
   // Meshing algorithms
   // Creates 6 faces for each voxel
-  pub fn simple<const CHUNK_SIZE: usize>(data: &[BlockId], texture_atlas: &Atlas<BlockId>) -> Vec<Vertex> {
+  pub fn simple(chunk: &Chunk, block_table: &BlockMeshTable) -> Vec<Vertex> {
     let mut vertices = vec![];
+    if chunk.blocks.single() == Some(BlockId::AIR) {
+      return vertices;
+    }
+
     for i in 0 .. CHUNK_SIZE as isize {
       for j in 0 .. CHUNK_SIZE as isize {
         for k in 0 .. CHUNK_SIZE as isize {
-          let block_state = data[index::<CHUNK_SIZE>(i, j, k)];
-          if block_state != BlockId::AIR {
-            let uv = texture_atlas.uv(&block_state);
-            vertices.extend(block_face(Side::Top,    i, j, k, uv));
-            vertices.extend(block_face(Side::Bottom, i, j, k, uv));
-            vertices.extend(block_face(Side::Right,  i, j, k, uv));
-            vertices.extend(block_face(Side::Left,   i, j, k, uv));
-            vertices.extend(block_face(Side::Front,  i, j, k, uv));
-            vertices.extend(block_face(Side::Back,   i, j, k, uv));
+          let block_state = chunk.get_block(i as usize, j as usize, k as usize);
+          if let Some(faces) = block_table.faces(block_state) {
+            for side in Side::ALL {
+              vertices.extend(block_face(side, i, j, k, faces[side as usize]));
+            }
           }
         }
 
@@ -90,21 +137,30 @@ pub mod mesher {
   }
 
   // Creates only the faces visible from outside
-  pub fn culled<const CHUNK_SIZE: usize>(data: &[BlockId], texture_atlas: &Atlas<BlockId>) -> Vec<Vertex> {
+  pub fn culled(chunk: &Chunk, block_table: &BlockMeshTable) -> Vec<Vertex> {
     let mut vertices = vec![];
+    if chunk.blocks.single() == Some(BlockId::AIR) {
+      return vertices;
+    }
+
+    // faces on the chunk border are always visible, as neighbouring chunks are unknown here
+    let is_hidden_by = |x: isize, y: isize, z: isize| -> bool {
+      let chunk_size = CHUNK_SIZE as isize;
+      if x < 0 || y < 0 || z < 0 || x >= chunk_size || y >= chunk_size || z >= chunk_size { return false; }
+      return block_table.is_opaque(chunk.get_block(x as usize, y as usize, z as usize));
+    };
+
     for i in 0 .. CHUNK_SIZE as isize {
       for j in 0 .. CHUNK_SIZE as isize {
         for k in 0 .. CHUNK_SIZE as isize {
-          let block_state = data[index::<CHUNK_SIZE>(i, j, k)];
-          if block_state != BlockId::AIR {
-            let uv = texture_atlas.uv(&block_state);
-            let chunk_volume: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
-            let v = index::<CHUNK_SIZE>(i, j + 1, k); if v != chunk_volume { if unsafe { *data.get_unchecked(v) == BlockId::AIR } { vertices.extend(block_face(Side::Top,    i, j, k, uv)); } } else { vertices.extend(block_face(Side::Top,    i, j, k, uv)); }
-            let v = index::<CHUNK_SIZE>(i, j - 1, k); if v != chunk_volume { if unsafe { *data.get_unchecked(v) == BlockId::AIR } { vertices.extend(block_face(Side::Bottom, i, j, k, uv)); } } else { vertices.extend(block_face(Side::Bottom, i, j, k, uv)); }
-            let v = index::<CHUNK_SIZE>(i, j, k + 1); if v != chunk_volume { if unsafe { *data.get_unchecked(v) == BlockId::AIR } { vertices.extend(block_face(Side::Right,  i, j, k, uv)); } } else { vertices.extend(block_face(Side::Right,  i, j, k, uv)); }
-            let v = index::<CHUNK_SIZE>(i, j, k - 1); if v != chunk_volume { if unsafe { *data.get_unchecked(v) == BlockId::AIR } { vertices.extend(block_face(Side::Left,   i, j, k, uv)); } } else { vertices.extend(block_face(Side::Left,   i, j, k, uv)); }
-            let v = index::<CHUNK_SIZE>(i + 1, j, k); if v != chunk_volume { if unsafe { *data.get_unchecked(v) == BlockId::AIR } { vertices.extend(block_face(Side::Front,  i, j, k, uv)); } } else { vertices.extend(block_face(Side::Front,  i, j, k, uv)); }
-            let v = index::<CHUNK_SIZE>(i - 1, j, k); if v != chunk_volume { if unsafe { *data.get_unchecked(v) == BlockId::AIR } { vertices.extend(block_face(Side::Back,   i, j, k, uv)); } } else { vertices.extend(block_face(Side::Back,   i, j, k, uv)); }
+          let block_state = chunk.get_block(i as usize, j as usize, k as usize);
+          if let Some(faces) = block_table.faces(block_state) {
+            for side in Side::ALL {
+              let [dx, dy, dz] = side.normal().to_array().map(|x| x as isize);
+              if !is_hidden_by(i + dx, j + dy, k + dz) {
+                vertices.extend(block_face(side, i, j, k, faces[side as usize]));
+              }
+            }
           }
         }
 
@@ -114,13 +170,6 @@ pub mod mesher {
 
     return vertices;
   }
-
-  const fn index<const CHUNK_SIZE: usize>(x: isize, y: isize, z: isize) -> usize {
-    let chunk_size = CHUNK_SIZE as isize;
-    let chunk_volume = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as isize;
-    return if x < 0 || y < 0 || z < 0 || x >= chunk_size || y >= chunk_size || z >= chunk_size { chunk_volume as usize }
-    else { ((z * chunk_size * chunk_size) + (y * chunk_size) + x) as usize };
-  }
 }
 
 pub const fn block_face(side: Side, i: isize, j: isize, k: isize, uv: Vec4) -> [Vertex; 6] {
@@ -1,5 +1,5 @@
 use bytemuck::{Pod, Zeroable};
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Quat, Vec3};
 use crate::graphics::instance::Instance;
 
 unsafe impl Zeroable for BakedEntityModel { }
@@ -7,7 +7,8 @@ unsafe impl Pod for BakedEntityModel { }
 
 #[derive(Copy, Clone)]
 pub struct EntityModel {
-  pub position: Vec3,
+  pub position : Vec3,
+  pub rotation : Quat,
 }
 
 #[derive(Copy, Clone)]
@@ -20,7 +21,8 @@ impl Instance for EntityModel {
 
   fn bake(&self) -> Self::Baked {
     return BakedEntityModel {
-      model: Mat4::from_translation(self.position),
+      // the mesh spans from the origin to (1, 1, 1), it turns around its center
+      model: Mat4::from_translation(self.position + 0.5) * Mat4::from_quat(self.rotation) * Mat4::from_translation(Vec3::splat(-0.5)),
     }
   }
 
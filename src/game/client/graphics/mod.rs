@@ -2,4 +2,5 @@ pub mod chunk_model;
 pub mod chunk_renderer;
 pub mod entity_model;
 pub mod entity_renderer;
+pub mod textures;
 pub mod world_renderer;
@@ -0,0 +1,30 @@
+// Textures embedded into the client, block definitions refer to them by name
+pub const MISSING_TEXTURE: &str = "test";
+
+pub const BLOCK_TEXTURES: &[(&str, &[u8])] = &[
+  ("test",  include_bytes!("../../../../res/test.png")),
+  ("panel", include_bytes!("../../../../res/panel.png")),
+
+  ("stone",      include_bytes!("../../../../res/stone.png")),
+  ("dirt",       include_bytes!("../../../../res/dirt.png")),
+  ("grass_top",  include_bytes!("../../../../res/grass_top.png")),
+  ("grass_side", include_bytes!("../../../../res/grass_side.png")),
+  ("sand",       include_bytes!("../../../../res/sand.png")),
+  ("snow",       include_bytes!("../../../../res/snow.png")),
+
+  ("log_side",    include_bytes!("../../../../res/log_side.png")),
+  ("log_top",     include_bytes!("../../../../res/log_top.png")),
+  ("leaves",      include_bytes!("../../../../res/leaves.png")),
+  ("cobblestone", include_bytes!("../../../../res/cobblestone.png")),
+  ("planks",      include_bytes!("../../../../res/planks.png")),
+  ("coal_ore",    include_bytes!("../../../../res/coal_ore.png")),
+  ("iron_ore",    include_bytes!("../../../../res/iron_ore.png")),
+];
+
+/// Resolves a texture name to the key it's stored under in the atlas, falling back to the missing texture.
+pub fn texture_key(name: &str) -> &'static str {
+  return BLOCK_TEXTURES.iter()
+    .find(|(key, _)| *key == name)
+    .map(|(key, _)| *key)
+    .unwrap_or(MISSING_TEXTURE);
+}
\ No newline at end of file
@@ -5,7 +5,8 @@ use crate::app::{App, UVxlEvent};
 use crate::game::client::graphics::chunk_renderer::ChunkRenderer;
 use crate::game::client::graphics::chunk_model::ChunkModel;
 use crate::game::client::graphics::entity_renderer::EntityRenderer;
-use crate::game::world::BlockId;
+use crate::game::client::graphics::textures::BLOCK_TEXTURES;
+use crate::game::world::block_registry::BlockRegistry;
 use crate::graphics::atlas::Atlas;
 use crate::graphics::bindable::Bindable;
 use crate::graphics::camera::{Camera3D, Projection, ProjectionPerspective, TagCamera3D};
@@ -25,13 +26,14 @@ pub struct WorldRenderer {
 }
 
 impl WorldRenderer {
-  pub fn new(app: &App) -> Self {
+  pub fn new(app: &App, registry: &BlockRegistry) -> Self {
     let graphics = &app.graphics;
 
-    let atlas = Atlas::new(&[
-      (BlockId::TEST, image::load_from_memory(include_bytes!("../../../../res/test.png")).unwrap().flipv()),
-      (BlockId::PANEL, image::load_from_memory(include_bytes!("../../../../res/panel.png")).unwrap().flipv()),
-    ], graphics);
+    let textures = BLOCK_TEXTURES.iter()
+      .map(|(name, data)| (*name, image::load_from_memory(data).unwrap().flipv()))
+      .collect::<Vec<_>>();
+
+    let atlas = Atlas::new(&textures, graphics);
 
     let depth_buffer = DepthBuffer::new(graphics, UVec2::new(graphics.size.width, graphics.config.height));
 
@@ -110,7 +112,7 @@ impl WorldRenderer {
       proxy.send_event(UVxlEvent::MesherChunkDone(position, data)).unwrap();
     };
 
-    let chunk_renderer = ChunkRenderer::new(atlas, sender);
+    let chunk_renderer = ChunkRenderer::new(atlas, registry, sender);
     let entity_renderer = EntityRenderer::new(graphics);
 
     return Self {
@@ -1,3 +1,4 @@
 pub mod window;
 pub mod graphics;
-pub mod client;
\ No newline at end of file
+pub mod client;
+pub mod chat;
\ No newline at end of file
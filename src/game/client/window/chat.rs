@@ -0,0 +1,133 @@
+use egui::{Align2, Color32, RichText};
+use egui::text::{CCursor, CCursorRange};
+use log::error;
+
+use crate::app::App;
+use crate::game::chat::DEFAULT_MAX_CHAT_LENGTH;
+use crate::game::client::chat::ChatLine;
+use crate::game::network::packet::{ChatClientPacket, ClientPacket, CompletionRequestClientPacket};
+
+use super::{Window, WindowId};
+
+// Key which opens the chat, Enter sends the message and Escape discards it
+const OPEN_KEY: egui::Key = egui::Key::T;
+
+// Asks the server to complete the command being typed
+const COMPLETE_KEY: egui::Key = egui::Key::Tab;
+
+/// Chat in the bottom left corner, recent messages fade out while it's closed.
+#[derive(Default)]
+pub struct ChatWindow {
+  input       : String,
+  focus       : bool, // the input line takes the focus once it's shown
+  suggestions : Vec<String>, // completions which didn't agree, listed until the input changes
+}
+
+impl Window for ChatWindow {
+  fn draw(&mut self, app: &mut App) {
+    if app.connection.is_none() {
+      return;
+    }
+
+    let context = app.egui_ctx.context.clone();
+    if !app.chat.open {
+      // the key's text event belongs to this frame, the input line only appears in the next one
+      if context.input(|i| i.key_pressed(OPEN_KEY)) {
+        app.chat.open = true;
+        self.focus = true;
+      }
+
+      egui::Area::new("chat")
+        .anchor(Align2::LEFT_BOTTOM, (8.0, -8.0))
+        .interactable(false)
+        .show(&context, |ui|
+      {
+        for line in app.chat.recent() {
+          ui.label(format_line(line));
+        }
+      });
+
+      return;
+    }
+
+    egui::Window::new("Chat")
+      .collapsible(false)
+      .resizable(false)
+      .fixed_size((384.0, 0.0))
+      .anchor(Align2::LEFT_BOTTOM, (8.0, -8.0))
+      .show(&context, |ui|
+    {
+      egui::ScrollArea::vertical()
+        .max_height(192.0)
+        .stick_to_bottom(true)
+        .auto_shrink([false, true])
+        .show(ui, |ui|
+      {
+        for line in &app.chat.lines {
+          ui.label(format_line(line));
+        }
+      });
+
+      // tab stays in the input line instead of moving the focus
+      let mut output = egui::TextEdit::singleline(&mut self.input)
+        .char_limit(DEFAULT_MAX_CHAT_LENGTH)
+        .desired_width(f32::INFINITY)
+        .lock_focus(true)
+        .show(ui);
+
+      let edit = output.response;
+      if edit.changed() {
+        self.suggestions.clear();
+      }
+
+      if std::mem::take(&mut self.focus) {
+        edit.request_focus();
+      }
+
+      if let Some(completion) = app.chat.completion.take() {
+        if let Some(completed) = completion.apply(&self.input) {
+          self.input = completed;
+          self.suggestions = if completion.suggestions.len() > 1 { completion.suggestions } else { Vec::new() };
+
+          output.state.set_ccursor_range(Some(CCursorRange::one(CCursor::new(self.input.chars().count()))));
+          output.state.store(ui.ctx(), edit.id);
+        }
+      }
+
+      if !self.suggestions.is_empty() {
+        ui.label(RichText::new(self.suggestions.join("  ")).color(Color32::GRAY));
+      }
+
+      if self.input.starts_with('/') && ui.input(|i| i.key_pressed(COMPLETE_KEY)) {
+        if let Some(connection) = app.connection.as_mut() {
+          connection.send(ClientPacket::CompletionRequestClientPacket(CompletionRequestClientPacket { input: self.input.clone() }))
+            .unwrap_or_else(|err| error!("Failed to request completions: {}", err));
+        }
+      }
+
+      if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+        let message = std::mem::take(&mut self.input);
+        self.suggestions.clear();
+        if let Some(connection) = app.connection.as_mut().filter(|_| !message.trim().is_empty()) {
+          connection.send(ClientPacket::ChatClientPacket(ChatClientPacket { message }))
+            .unwrap_or_else(|err| error!("Failed to send chat message: {}", err));
+        }
+
+        app.chat.open = false;
+      } else if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+        self.input.clear();
+        self.suggestions.clear();
+        app.chat.open = false;
+      }
+    });
+  }
+
+  fn id(&self) -> WindowId { WindowId::Chat }
+}
+
+fn format_line(line: &ChatLine) -> RichText {
+  return match &line.sender {
+    Some(sender) => RichText::new(format!("<{}> {}", sender, line.message)).color(Color32::WHITE),
+    None => RichText::new(&line.message).color(Color32::YELLOW),
+  };
+}
\ No newline at end of file
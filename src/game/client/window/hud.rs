@@ -0,0 +1,32 @@
+use egui::Align2;
+
+use crate::app::App;
+
+use super::{Window, WindowId};
+
+/// Connection stats shown in the corner while playing.
+#[derive(Default)]
+pub struct HudWindow;
+
+impl Window for HudWindow {
+  fn draw(&mut self, app: &mut App) {
+    if app.connection.is_none() {
+      return;
+    }
+
+    egui::Area::new("hud")
+      .anchor(Align2::RIGHT_TOP, (-8.0, 8.0))
+      .interactable(false)
+      .show(&app.egui_ctx.context, |ui|
+    {
+      let ping = match app.keepalive.rtt() {
+        Some(rtt) => format!("Ping: {} ms", rtt.as_millis()),
+        None => String::from("Ping: -"),
+      };
+
+      ui.label(egui::RichText::new(ping).color(egui::Color32::WHITE));
+    });
+  }
+
+  fn id(&self) -> WindowId { WindowId::Hud }
+}
\ No newline at end of file
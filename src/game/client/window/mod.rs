@@ -1,4 +1,6 @@
 pub mod server_join;
+pub mod hud;
+pub mod chat;
 
 use crate::app::App;
 
@@ -12,4 +14,6 @@ pub trait Window {
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum WindowId {
   ServerJoin,
+  Hud,
+  Chat,
 }
\ No newline at end of file
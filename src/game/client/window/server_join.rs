@@ -3,6 +3,7 @@ use log::error;
 use winit::window::CursorGrabMode;
 
 use crate::{app::{App, UVxlEvent}, network::connection::Connection};
+use crate::game::network::keepalive::Keepalive;
 
 use super::{Window, WindowId};
 
@@ -38,7 +39,13 @@ impl Window for ServerJoinWindow {
         let edit = ui.text_edit_singleline(&mut self.address);
         let button = ui.button("Join");
 
+        if let Some(reason) = &app.disconnect_reason {
+          ui.colored_label(egui::Color32::LIGHT_RED, reason);
+        }
+
         if button.clicked() || edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+          app.disconnect_reason = None;
+
           if let Err(err) = app.event_proxy.send_event(UVxlEvent::SetClientName(self.name.clone())) {
             error!("Failed to send UVxl event: {}", err);
           }
@@ -46,6 +53,10 @@ impl Window for ServerJoinWindow {
           let Ok(address) = self.address.parse() else { todo!() };
           let Ok(connection) = Connection::new(address, app.event_proxy.clone()) else { todo!() };
           app.connection = Some(connection);
+
+          // the handshake is sent once the connection is open, the keepalive starts over then as well
+          app.handshake_sent = false;
+          app.keepalive = Keepalive::default();
           app.window.set_cursor_grab(CursorGrabMode::Locked)
             .unwrap_or_else(|err| error!("Failed to confine mouse cursor: {}", err));
         }
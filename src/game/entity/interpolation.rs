@@ -0,0 +1,80 @@
+use std::collections::VecDeque;
+use glam::{Quat, Vec3};
+use instant::{Duration, Instant};
+
+/// How far behind real time remote entities are shown, long enough for the next update to arrive in time.
+pub const INTERPOLATION_DELAY: Duration = Duration::from_millis(100);
+
+// Entities keep moving for at most this long after their last update, then they stop
+const MAX_EXTRAPOLATION: Duration = Duration::from_millis(250);
+
+// About a second and a half of updates at the client tick rate
+const MAX_SNAPSHOTS: usize = 32;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Snapshot {
+  pub time     : Instant,
+  pub position : Vec3,
+  pub rotation : Quat,
+}
+
+/// Positions and rotations of a remote entity in the order they were received.
+///
+/// Sampling a point in time between two snapshots interpolates between them, sampling past
+/// the last one extrapolates the position with the most recent velocity for a short while
+/// and keeps the last rotation.
+#[derive(Debug, Default)]
+pub struct SnapshotBuffer {
+  snapshots: VecDeque<Snapshot>,
+}
+
+impl SnapshotBuffer {
+  pub fn push(&mut self, time: Instant, position: Vec3, rotation: Quat) {
+    // the clock doesn't go backwards, but two updates may be handled at the same instant
+    if let Some(last) = self.snapshots.back_mut() {
+      if time <= last.time {
+        last.position = position;
+        last.rotation = rotation;
+        return;
+      }
+    }
+
+    if self.snapshots.len() == MAX_SNAPSHOTS {
+      self.snapshots.pop_front();
+    }
+
+    self.snapshots.push_back(Snapshot { time, position, rotation });
+  }
+
+  pub fn sample(&self, time: Instant) -> Option<(Vec3, Quat)> {
+    let first = self.snapshots.front()?;
+    let last = self.snapshots.back()?;
+    if time <= first.time {
+      return Some((first.position, first.rotation));
+    }
+
+    if time >= last.time {
+      return Some((self.extrapolate(time), last.rotation));
+    }
+
+    // the first snapshot after `time`, there is one before it as well
+    let next = self.snapshots.partition_point(|x| x.time <= time);
+    let (a, b) = (self.snapshots[next - 1], self.snapshots[next]);
+    let t = (time - a.time).as_secs_f32() / (b.time - a.time).as_secs_f32();
+
+    return Some((a.position.lerp(b.position, t), a.rotation.slerp(b.rotation, t)));
+  }
+
+  fn extrapolate(&self, time: Instant) -> Vec3 {
+    let last = self.snapshots[self.snapshots.len() - 1];
+    if self.snapshots.len() < 2 {
+      return last.position;
+    }
+
+    let previous = self.snapshots[self.snapshots.len() - 2];
+    let velocity = (last.position - previous.position) / (last.time - previous.time).as_secs_f32();
+    let elapsed = (time - last.time).min(MAX_EXTRAPOLATION);
+
+    return last.position + velocity * elapsed.as_secs_f32();
+  }
+}
\ No newline at end of file
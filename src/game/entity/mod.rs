@@ -2,6 +2,9 @@ use glam::{Quat, Vec3};
 use serde::{Deserialize, Serialize};
 
 pub mod player;
+pub mod interpolation;
+pub mod orientation;
+pub mod prediction;
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct EntityState {
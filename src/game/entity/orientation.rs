@@ -0,0 +1,35 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+use glam::Quat;
+use serde::{Deserialize, Serialize};
+
+/// Where an entity is looking, quantized to fit in four bytes on the wire.
+///
+/// Yaw wraps around the full circle, pitch is limited to straight up and down, both in the
+/// same radians as the camera uses.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Orientation {
+  yaw   : u16, // fraction of a full turn
+  pitch : i16, // fraction of a quarter turn
+}
+
+impl Orientation {
+  pub fn from_angles(yaw: f32, pitch: f32) -> Self {
+    return Self {
+      yaw   : (yaw.rem_euclid(TAU) / TAU * 65536.0).round() as u32 as u16,
+      pitch : (pitch.clamp(-FRAC_PI_2, FRAC_PI_2) / FRAC_PI_2 * i16::MAX as f32).round() as i16,
+    };
+  }
+
+  pub fn yaw(&self) -> f32 {
+    return self.yaw as f32 / 65536.0 * TAU;
+  }
+
+  pub fn pitch(&self) -> f32 {
+    return self.pitch as f32 / i16::MAX as f32 * FRAC_PI_2;
+  }
+
+  /// Rotates +X onto the direction the camera looks in for these angles.
+  pub fn rotation(&self) -> Quat {
+    return Quat::from_rotation_y(-self.yaw()) * Quat::from_rotation_z(self.pitch());
+  }
+}
\ No newline at end of file
@@ -0,0 +1,82 @@
+use std::collections::VecDeque;
+use glam::Vec3;
+
+// Differences smaller than this are rounding errors rather than corrections
+const CORRECTION_THRESHOLD: f32 = 0.01;
+
+// Several seconds of moves at the client tick rate, the connection times out long before
+const MAX_PENDING_MOVES: usize = 1024;
+
+#[derive(Clone, Copy, Debug)]
+struct PendingMove {
+  sequence : u32,
+  movement : Vec3,
+}
+
+/// Moves the client already applied locally but the server hasn't acknowledged yet.
+///
+/// Each move is the distance travelled since the previous one and carries a sequence number.
+/// The server acknowledges moves with its own position of the player, the moves sent after the
+/// acknowledged one are replayed on top of it, so a correction doesn't undo them.
+#[derive(Debug)]
+pub struct MovementPrediction {
+  next_sequence : u32,
+  acknowledged  : u32, // sequence number of the latest acknowledged move
+  sent          : Vec3, // where the player was when the last move was recorded
+  pending       : VecDeque<PendingMove>,
+}
+
+impl MovementPrediction {
+  pub fn new(position: Vec3) -> Self {
+    return Self {
+      next_sequence : 0,
+      acknowledged  : u32::MAX, // right before the first move
+      sent          : position,
+      pending       : VecDeque::new(),
+    };
+  }
+
+  /// Records the movement since the last recorded move, returns its sequence number and the movement to send.
+  pub fn record(&mut self, position: Vec3) -> (u32, Vec3) {
+    let sequence = self.next_sequence;
+    let movement = position - self.sent;
+    self.next_sequence = self.next_sequence.wrapping_add(1);
+    self.sent = position;
+
+    if self.pending.len() == MAX_PENDING_MOVES {
+      self.pending.pop_front();
+    }
+
+    self.pending.push_back(PendingMove { sequence, movement });
+    return (sequence, movement);
+  }
+
+  /// Takes the server's position after the move with this sequence number. Returns where the player
+  /// should be now if the prediction was off, `current` is its position including unrecorded movement.
+  pub fn acknowledge(&mut self, sequence: u32, position: Vec3, current: Vec3) -> Option<Vec3> {
+    // sequence numbers wrap around, anything up to half the range behind counts as not newer. The latest
+    // move may be acknowledged again, when the server moved the player on its own, e.g. by teleporting it
+    let up_to = |a: u32, b: u32| b.wrapping_sub(a) < u32::MAX / 2;
+    if sequence != self.acknowledged && up_to(sequence, self.acknowledged) {
+      return None;
+    }
+
+    self.acknowledged = sequence;
+    while self.pending.front().is_some_and(|x| up_to(x.sequence, sequence)) {
+      self.pending.pop_front();
+    }
+
+    let predicted = self.pending.iter().fold(position, |position, x| position + x.movement);
+    if predicted.distance(self.sent) <= CORRECTION_THRESHOLD {
+      return None;
+    }
+
+    let correction = predicted - self.sent;
+    self.sent = predicted;
+    return Some(current + correction);
+  }
+
+  pub fn pending(&self) -> usize {
+    return self.pending.len();
+  }
+}
\ No newline at end of file
@@ -2,6 +2,7 @@ pub mod network;
 pub mod world;
 pub mod entity;
 pub mod player;
+pub mod chat;
 
 #[cfg(feature = "client")]
 pub mod client;
\ No newline at end of file
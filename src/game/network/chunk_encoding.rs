@@ -0,0 +1,214 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use crate::game::world::BlockId;
+use crate::game::world::biome::BiomeId;
+use crate::game::world::chunk::{Chunk, CHUNK_AREA, CHUNK_SIZE, CHUNK_VOLUME};
+use crate::game::world::palette::PalettedContainer;
+
+const DEFLATE_LEVEL: u8 = 6;
+
+// Larger than any run length encoded chunk, so a malicious packet can't inflate into gigabytes
+const MAX_INFLATED_SIZE: usize = 1024 * 1024;
+
+/// Ways a chunk can be encoded on the wire, clients announce the ones they can decode when joining.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ChunkEncoding {
+  /// The chunk's own serialized form with bit-packed palette indices, decoded by every client.
+  Paletted,
+  /// Palettes followed by runs of equal values, blocks are visited layer by layer.
+  RunLength,
+  /// `RunLength` compressed with deflate.
+  Deflate,
+}
+
+impl ChunkEncoding {
+  /// Every encoding this build can decode, most compact first.
+  pub const ALL: [ChunkEncoding; 3] = [Self::Deflate, Self::RunLength, Self::Paletted];
+
+  /// Picks the most compact encoding the client supports, falling back to `Paletted`.
+  pub fn negotiate(supported: &[ChunkEncoding], allow_compression: bool) -> Self {
+    return Self::ALL.into_iter()
+      .filter(|x| allow_compression || *x != Self::Deflate)
+      .find(|x| supported.contains(x))
+      .unwrap_or(Self::Paletted);
+  }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EncodedChunk {
+  pub encoding : ChunkEncoding,
+  pub data     : Vec<u8>,
+}
+
+impl EncodedChunk {
+  pub fn encode(chunk: &Chunk, encoding: ChunkEncoding) -> Result<Self> {
+    let data = match encoding {
+      ChunkEncoding::Paletted  => bincode::serialize(chunk)?,
+      ChunkEncoding::RunLength => bincode::serialize(&RunLengthChunk::encode(chunk))?,
+      ChunkEncoding::Deflate   => miniz_oxide::deflate::compress_to_vec(&bincode::serialize(&RunLengthChunk::encode(chunk))?, DEFLATE_LEVEL),
+    };
+
+    return Ok(Self { encoding, data });
+  }
+
+  /// Run length encodes pairs of palette index and run length as they are, to test decoding of corrupt chunks.
+  #[doc(hidden)]
+  pub fn from_runs(blocks: (Vec<BlockId>, &[(usize, usize)]), biomes: (Vec<BiomeId>, &[(usize, usize)])) -> Result<Self> {
+    let chunk = RunLengthChunk {
+      blocks : Runs::from_raw(blocks.0, blocks.1),
+      biomes : Runs::from_raw(biomes.0, biomes.1),
+    };
+
+    return Ok(Self { encoding: ChunkEncoding::RunLength, data: bincode::serialize(&chunk)? });
+  }
+
+  pub fn decode(&self) -> Result<Chunk> {
+    return match self.encoding {
+      ChunkEncoding::Paletted  => Ok(bincode::deserialize(&self.data)?),
+      ChunkEncoding::RunLength => bincode::deserialize::<RunLengthChunk>(&self.data)?.decode(),
+      ChunkEncoding::Deflate   => {
+        let data = miniz_oxide::inflate::decompress_to_vec_with_limit(&self.data, MAX_INFLATED_SIZE)
+          .map_err(|err| anyhow!("Failed to inflate chunk: {}", err))?;
+
+        bincode::deserialize::<RunLengthChunk>(&data)?.decode()
+      }
+    };
+  }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RunLengthChunk {
+  blocks : Runs<BlockId>,
+  biomes : Runs<BiomeId>,
+}
+
+impl RunLengthChunk {
+  fn encode(chunk: &Chunk) -> Self {
+    return Self {
+      blocks : Runs::encode(&chunk.blocks, block_order()),
+      biomes : Runs::encode(&chunk.biomes, 0 .. CHUNK_AREA),
+    };
+  }
+
+  fn decode(&self) -> Result<Chunk> {
+    return Ok(Chunk {
+      blocks : self.blocks.decode(block_order())?,
+      biomes : self.biomes.decode(0 .. CHUNK_AREA)?,
+    });
+  }
+}
+
+// Terrain is mostly made of horizontal layers, visiting a whole layer before the next one makes runs longer
+fn block_order() -> impl Iterator<Item = usize> {
+  return (0 .. CHUNK_VOLUME).map(|i| {
+    let (x, z, y) = (i % CHUNK_SIZE, i / CHUNK_SIZE % CHUNK_SIZE, i / CHUNK_AREA);
+    x + y * CHUNK_SIZE + z * CHUNK_AREA
+  });
+}
+
+// Values in the order they first appear and pairs of palette index and run length, both as varints
+#[derive(Serialize, Deserialize)]
+struct Runs<T> {
+  palette : Vec<T>,
+  runs    : Vec<u8>,
+}
+
+impl<T: Copy + Eq> Runs<T> {
+  fn encode<const LEN: usize>(container: &PalettedContainer<T, LEN>, order: impl Iterator<Item = usize>) -> Self {
+    let mut runs = Self { palette: Vec::new(), runs: Vec::new() };
+    let mut run: Option<(T, usize)> = None;
+
+    for value in order.map(|i| container.get(i)) {
+      match &mut run {
+        Some((current, length)) if *current == value => *length += 1,
+        _ => {
+          if let Some((current, length)) = run.replace((value, 1)) {
+            runs.push(current, length);
+          }
+        }
+      }
+    }
+
+    if let Some((current, length)) = run {
+      runs.push(current, length);
+    }
+
+    return runs;
+  }
+
+  fn from_raw(palette: Vec<T>, runs: &[(usize, usize)]) -> Self {
+    let mut data = Vec::new();
+    for (index, length) in runs {
+      write_varint(&mut data, *index);
+      write_varint(&mut data, *length);
+    }
+
+    return Self { palette, runs: data };
+  }
+
+  fn push(&mut self, value: T, length: usize) {
+    let index = match self.palette.iter().position(|x| *x == value) {
+      Some(index) => index,
+      None => {
+        self.palette.push(value);
+        self.palette.len() - 1
+      }
+    };
+
+    write_varint(&mut self.runs, index);
+    write_varint(&mut self.runs, length);
+  }
+
+  fn decode<const LEN: usize>(&self, mut order: impl Iterator<Item = usize>) -> Result<PalettedContainer<T, LEN>> {
+    let first = self.palette.first().ok_or_else(|| anyhow!("Chunk data has an empty palette"))?;
+    let mut container = PalettedContainer::new(*first);
+    let mut remaining = self.runs.as_slice();
+    let mut filled = 0;
+
+    while !remaining.is_empty() {
+      let index = read_varint(&mut remaining)?;
+      let length = read_varint(&mut remaining)?;
+      let value = *self.palette.get(index).ok_or_else(|| anyhow!("Chunk data palette index {} out of bounds", index))?;
+      if length == 0 || length > LEN - filled {
+        return Err(anyhow!("Chunk data runs don't add up to {} values", LEN));
+      }
+
+      for i in order.by_ref().take(length) {
+        container.set(i, value);
+      }
+
+      filled += length;
+    }
+
+    if filled != LEN {
+      return Err(anyhow!("Chunk data runs cover {} of {} values", filled, LEN));
+    }
+
+    return Ok(container);
+  }
+}
+
+// LEB128, seven bits per byte with the high bit set on every byte but the last
+fn write_varint(buffer: &mut Vec<u8>, mut value: usize) {
+  while value >= 0x80 {
+    buffer.push(value as u8 | 0x80);
+    value >>= 7;
+  }
+
+  buffer.push(value as u8);
+}
+
+fn read_varint(buffer: &mut &[u8]) -> Result<usize> {
+  let mut value = 0;
+  for shift in (0 .. usize::BITS).step_by(7) {
+    let (byte, rest) = buffer.split_first().ok_or_else(|| anyhow!("Chunk data is truncated"))?;
+    *buffer = rest;
+
+    value |= ((byte & 0x7F) as usize) << shift;
+    if byte & 0x80 == 0 {
+      return Ok(value);
+    }
+  }
+
+  return Err(anyhow!("Chunk data contains an overlong varint"));
+}
\ No newline at end of file
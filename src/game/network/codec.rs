@@ -0,0 +1,100 @@
+use std::fmt::{Display, Formatter};
+use tokio_util::bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Largest frame the server sends and clients accept, big enough for any chunk. Both ends must agree on it,
+/// so it's part of the protocol rather than a setting.
+pub const MAX_FRAME_SIZE: usize = 8 * 1024 * 1024;
+
+// Frames are prefixed with their length as a big endian u32
+const HEADER_SIZE: usize = 4;
+
+#[derive(Debug)]
+pub enum FrameError {
+  Oversized { size: usize, max: usize },
+  Io(std::io::Error),
+}
+
+impl Display for FrameError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::Oversized { size, max } => write!(f, "Frame of {} bytes exceeds the limit of {} bytes", size, max),
+      Self::Io(err) => write!(f, "{}", err),
+    };
+  }
+}
+
+impl std::error::Error for FrameError {}
+
+impl From<std::io::Error> for FrameError {
+  fn from(err: std::io::Error) -> Self {
+    return Self::Io(err);
+  }
+}
+
+/// Length-delimited framing used by both ends of the native transport.
+///
+/// TCP may split a packet across several reads or deliver several packets at once, the
+/// decoder buffers bytes until a whole frame has arrived and yields frames one by one.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameCodec {
+  max_frame_size: usize,
+}
+
+impl Default for FrameCodec {
+  fn default() -> Self {
+    return Self::new(MAX_FRAME_SIZE);
+  }
+}
+
+impl FrameCodec {
+  pub fn new(max_frame_size: usize) -> Self {
+    return Self { max_frame_size: max_frame_size.min(u32::MAX as usize) };
+  }
+
+  pub fn max_frame_size(&self) -> usize {
+    return self.max_frame_size;
+  }
+}
+
+impl Decoder for FrameCodec {
+  type Item = BytesMut;
+  type Error = FrameError;
+
+  fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+    if src.len() < HEADER_SIZE {
+      return Ok(None);
+    }
+
+    let size = u32::from_be_bytes([src[0], src[1], src[2], src[3]]) as usize;
+    if size > self.max_frame_size {
+      return Err(FrameError::Oversized { size, max: self.max_frame_size });
+    }
+
+    if src.len() < HEADER_SIZE + size {
+      // make room for the rest of the frame at once
+      src.reserve(HEADER_SIZE + size - src.len());
+      return Ok(None);
+    }
+
+    src.advance(HEADER_SIZE);
+    return Ok(Some(src.split_to(size)));
+  }
+}
+
+impl<T: AsRef<[u8]>> Encoder<T> for FrameCodec {
+  type Error = FrameError;
+
+  fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+    let data = item.as_ref();
+    if data.len() > self.max_frame_size {
+      return Err(FrameError::Oversized { size: data.len(), max: self.max_frame_size });
+    }
+
+    dst.reserve(HEADER_SIZE + data.len());
+    dst.put_u32(data.len() as u32);
+    dst.put_slice(data);
+
+    return Ok(());
+  }
+}
\ No newline at end of file
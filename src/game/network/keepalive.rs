@@ -0,0 +1,75 @@
+use instant::{Duration, Instant};
+
+/// How often each side pings the other one, unless configured otherwise.
+pub const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Connections which haven't received anything for this long are considered dead.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Tracks the liveness and round trip time of one end of a connection.
+///
+/// Pings carry the time they were sent at in microseconds since the tracker was created, the
+/// other side echoes it back in a pong, so no state has to be kept for pings in flight.
+#[derive(Debug)]
+pub struct Keepalive {
+  epoch         : Instant,
+  last_ping     : Instant,
+  last_received : Instant,
+  rtt           : Option<Duration>, // smoothed over recent pongs
+}
+
+impl Default for Keepalive {
+  fn default() -> Self {
+    return Self::new(Instant::now());
+  }
+}
+
+impl Keepalive {
+  pub fn new(now: Instant) -> Self {
+    return Self {
+      epoch         : now,
+      last_ping     : now,
+      last_received : now,
+      rtt           : None,
+    };
+  }
+
+  /// Any packet counts as a sign of life, not just pongs.
+  pub fn received(&mut self, now: Instant) {
+    self.last_received = now;
+  }
+
+  pub fn ping_due(&self, now: Instant, interval: Duration) -> bool {
+    return now.saturating_duration_since(self.last_ping) >= interval;
+  }
+
+  /// Returns the payload of a new ping.
+  pub fn ping(&mut self, now: Instant) -> u64 {
+    self.last_ping = now;
+    return now.saturating_duration_since(self.epoch).as_micros() as u64;
+  }
+
+  /// Measures the round trip of the ping the pong answers, returns `None` for payloads which weren't sent yet.
+  pub fn pong(&mut self, now: Instant, payload: u64) -> Option<Duration> {
+    let sent = self.epoch.checked_add(Duration::from_micros(payload))?;
+    if sent > now {
+      return None;
+    }
+
+    let sample = now - sent;
+    self.rtt = Some(match self.rtt {
+      Some(rtt) => (rtt * 7 + sample) / 8,
+      None => sample,
+    });
+
+    return Some(sample);
+  }
+
+  pub fn rtt(&self) -> Option<Duration> {
+    return self.rtt;
+  }
+
+  pub fn timed_out(&self, now: Instant, timeout: Duration) -> bool {
+    return now.saturating_duration_since(self.last_received) >= timeout;
+  }
+}
\ No newline at end of file
@@ -1 +1,6 @@
-pub mod packet;
\ No newline at end of file
+pub mod packet;
+pub mod chunk_encoding;
+pub mod keepalive;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod codec;
\ No newline at end of file
@@ -1,33 +1,68 @@
 use glam::{IVec3, Vec3};
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
-use crate::game::world::chunk::Chunk;
+use crate::game::entity::orientation::Orientation;
+use crate::game::network::chunk_encoding::{ChunkEncoding, EncodedChunk};
+use crate::game::world::BlockId;
+use crate::game::world::block_registry::BlockRegistry;
+
+/// Version of the packet format, must be incremented whenever packets change, as bincode encodes
+/// enum variants by their declaration order.
+pub const PROTOCOL_VERSION: u32 = 11;
+
+/// Identifies the build in version mismatch messages.
+pub const BUILD_ID: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
 
 pub trait Respondable {
   type Response;
 }
 
 // server packets
+// `ErrorServerPacket` must stay first, so clients of any version can read why they were rejected
 #[repr(u8)]
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ServerPacket {
   ErrorServerPacket(ErrorServerPacket),
   ClientJoinSuccessServerPacket(ClientJoinSuccessServerPacket),
-  PlayerJoinServerPacket(PlayerJoinServerPacket),
+  PlayerSpawnServerPacket(PlayerSpawnServerPacket),
   PlayerMoveServerPacket(PlayerMoveServerPacket),
+  PlayerDespawnServerPacket(PlayerDespawnServerPacket),
   InitialChunkDataServerPacket(InitialChunkDataServerPacket),
+  BlockUpdateServerPacket(BlockUpdateServerPacket),
+  ChunkUnloadServerPacket(ChunkUnloadServerPacket),
+  PingServerPacket(PingPacket),
+  PongServerPacket(PingPacket),
+  MoveAckServerPacket(MoveAckServerPacket),
+  MoveCorrectionServerPacket(MoveAckServerPacket),
+  ChatServerPacket(ChatServerPacket),
+  CompletionsServerPacket(CompletionsServerPacket),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ServerError {
-  PlayerLoggedIn
+  PlayerLoggedIn,
+  IncompatibleVersion {
+    protocol_version : u32,
+    build            : String,
+  },
+  Kicked {
+    reason : String,
+  },
+  Stopping,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct InitialPlayerData {
-  pub uuid     : Uuid,
-  pub name     : String,
-  pub position : Vec3,
+impl std::fmt::Display for ServerError {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      Self::PlayerLoggedIn => f.write_str("A player with this name is already connected to the server"),
+      Self::IncompatibleVersion { protocol_version, build } => write!(f,
+        "Incompatible versions: the server runs {} (protocol {}), this client is {} (protocol {})",
+        build, protocol_version, BUILD_ID, PROTOCOL_VERSION,
+      ),
+      Self::Kicked { reason } => write!(f, "Kicked from the server: {}", reason),
+      Self::Stopping => f.write_str("The server is stopping"),
+    };
+  }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -39,46 +74,136 @@ pub struct ErrorServerPacket {
 pub struct ClientJoinSuccessServerPacket {
   pub uuid     : Uuid,
   pub position : Vec3,
-  pub players  : Vec<InitialPlayerData>,
+  pub blocks   : BlockRegistry,
 }
 
+// Sent when a player comes into view, either by moving or by joining the game
 #[derive(Serialize, Deserialize, Debug)]
-pub struct PlayerJoinServerPacket {
-  pub uuid    : Uuid,
-  pub name    : String,
-  pub position: Vec3,
+pub struct PlayerSpawnServerPacket {
+  pub uuid        : Uuid,
+  pub name        : String,
+  pub position    : Vec3,
+  pub orientation : Orientation,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct PlayerMoveServerPacket {
-  pub uuid     : Uuid,
-  pub position : Vec3,
+  pub uuid        : Uuid,
+  pub position    : Vec3,
+  pub orientation : Orientation,
+}
+
+// Sent when a player leaves the view or the game
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlayerDespawnServerPacket {
+  pub uuid : Uuid,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct InitialChunkDataServerPacket {
-  pub chunk    : Chunk,
+  pub chunk    : EncodedChunk,
   pub position : IVec3,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BlockUpdateServerPacket {
+  pub position : IVec3,
+  pub block    : BlockId,
+}
+
+// Sent when a chunk leaves the player's view, the client drops it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChunkUnloadServerPacket {
+  pub position : IVec3,
+}
+
+// Keepalive in both directions, the receiver of a ping answers with a pong carrying the same payload
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PingPacket {
+  pub payload : u64,
+}
+
+// The server's position of the player after applying the client's move with this sequence number,
+// sent as a correction if the server refused the move
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MoveAckServerPacket {
+  pub sequence : u32,
+  pub position : Vec3,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChatServerPacket {
+  pub sender  : Option<String>, // none for messages of the server itself, e.g. players joining
+  pub message : String,
+}
+
+// Answers a completion request, the suggestions replace `input` from the byte offset `start` on
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CompletionsServerPacket {
+  pub input       : String, // as it was requested, the client ignores suggestions for input it changed since
+  pub start       : usize,
+  pub suggestions : Vec<String>,
+}
+
 // client packets
+// `HandshakeClientPacket` must stay first, so servers of any version can read it
 #[repr(u8)]
 #[derive(Serialize, Deserialize, Debug)]
 pub enum ClientPacket {
+  HandshakeClientPacket(HandshakeClientPacket),
   ClientJoinClientPacket(ClientJoinClientPacket),
   ClientMovePacket(ClientMovePacket),
+  BlockBreakClientPacket(BlockBreakClientPacket),
+  BlockPlaceClientPacket(BlockPlaceClientPacket),
+  PingClientPacket(PingPacket),
+  PongClientPacket(PingPacket),
+  ChatClientPacket(ChatClientPacket),
+  CompletionRequestClientPacket(CompletionRequestClientPacket),
+}
+
+// Sent before anything else, the server closes the connection if the protocol versions differ
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HandshakeClientPacket {
+  pub protocol_version : u32,
+  pub build            : String,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ClientJoinClientPacket {
-  pub name: String,
+  pub name            : String,
+  pub chunk_encodings : Vec<ChunkEncoding>, // the server sends chunks in the most compact one of these
 }
 
+// Distance travelled since the previous move, moves are numbered so the server can acknowledge them
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ClientMovePacket {
-  pub position: Vec3,
+  pub sequence    : u32,
+  pub movement    : Vec3,
+  pub orientation : Orientation,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BlockBreakClientPacket {
+  pub position: IVec3,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BlockPlaceClientPacket {
+  pub position : IVec3,
+  pub block    : BlockId,
 }
 
 impl Respondable for ClientJoinClientPacket {
   type Response = ClientJoinSuccessServerPacket;
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ChatClientPacket {
+  pub message : String,
+}
+
+// Asks for suggestions completing the last word of a command typed into the chat
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CompletionRequestClientPacket {
+  pub input : String,
 }
\ No newline at end of file
@@ -1,6 +1,12 @@
 use uuid::Uuid;
 use crate::game::entity::player::EntityPlayer;
 
+// Maximum distance from which a player can interact with blocks
+pub const PLAYER_REACH: f32 = 8.0;
+
+// Distance a player flies per second
+pub const PLAYER_SPEED: f32 = 20.0;
+
 #[derive(Debug)]
 pub struct Player {
   pub uuid: Uuid,
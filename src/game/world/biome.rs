@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd, Debug, Serialize, Deserialize)]
+pub struct BiomeId(u8);
+
+impl BiomeId {
+  pub const PLAINS    : BiomeId = BiomeId(0);
+  pub const FOREST    : BiomeId = BiomeId(1);
+  pub const DESERT    : BiomeId = BiomeId(2);
+  pub const MOUNTAINS : BiomeId = BiomeId(3);
+  pub const TUNDRA    : BiomeId = BiomeId(4);
+
+  pub const fn index(self) -> usize {
+    return self.0 as usize;
+  }
+
+  pub fn get(self) -> &'static Biome {
+    return &BIOMES[self.index()];
+  }
+
+  pub fn iter() -> impl Iterator<Item = BiomeId> {
+    return (0 .. BIOMES.len()).map(|x| BiomeId(x as u8));
+  }
+}
+
+#[derive(Debug)]
+pub struct Biome {
+  pub name : &'static str,
+
+  // position of the biome on the climate map, both within [0, 1]
+  pub temperature : f64,
+  pub humidity    : f64,
+
+  pub surface : &'static str, // topmost block of the column
+  pub filler  : &'static str, // blocks between the surface and stone
+
+  pub base_height        : f64,
+  pub height_amplitude   : f64,
+  pub vegetation_density : f64, // chance of a column being decorated
+}
+
+pub const BIOMES: &[Biome] = &[
+  Biome {
+    name: "plains",
+    temperature: 0.5, humidity: 0.45,
+    surface: "grass", filler: "dirt",
+    base_height: 34.0, height_amplitude: 8.0, vegetation_density: 0.002,
+  },
+  Biome {
+    name: "forest",
+    temperature: 0.55, humidity: 0.8,
+    surface: "grass", filler: "dirt",
+    base_height: 36.0, height_amplitude: 14.0, vegetation_density: 0.02,
+  },
+  Biome {
+    name: "desert",
+    temperature: 0.9, humidity: 0.1,
+    surface: "sand", filler: "sand",
+    base_height: 33.0, height_amplitude: 6.0, vegetation_density: 0.0,
+  },
+  Biome {
+    name: "mountains",
+    temperature: 0.35, humidity: 0.3,
+    surface: "stone", filler: "stone",
+    base_height: 52.0, height_amplitude: 40.0, vegetation_density: 0.001,
+  },
+  Biome {
+    name: "tundra",
+    temperature: 0.05, humidity: 0.5,
+    surface: "snow", filler: "dirt",
+    base_height: 34.0, height_amplitude: 10.0, vegetation_density: 0.001,
+  },
+];
\ No newline at end of file
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use crate::game::world::BlockId;
+use crate::util::side::Side;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlockTextures {
+  pub top    : String,
+  pub bottom : String,
+  pub right  : String,
+  pub left   : String,
+  pub front  : String,
+  pub back   : String,
+}
+
+impl BlockTextures {
+  pub fn get(&self, side: Side) -> &str {
+    return match side {
+      Side::Top    => &self.top,
+      Side::Bottom => &self.bottom,
+      Side::Right  => &self.right,
+      Side::Left   => &self.left,
+      Side::Front  => &self.front,
+      Side::Back   => &self.back,
+    };
+  }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlockProperties {
+  pub name           : String,
+  pub solid          : bool, // entities collide with the block
+  pub opaque         : bool, // hides faces of adjacent blocks
+  pub transparent    : bool, // has see-through texels
+  pub textures       : Option<BlockTextures>, // blocks without textures are not rendered
+  pub hardness       : f32, // negative hardness makes the block unbreakable
+  pub light_emission : u8,
+}
+
+/// Maps block ids to their properties, ids are assigned in definition order.
+///
+/// The server sends its registry to clients on join, so both sides agree on the id mapping.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "Vec<BlockProperties>", into = "Vec<BlockProperties>")]
+pub struct BlockRegistry {
+  blocks : Vec<BlockProperties>,
+  names  : HashMap<String, BlockId>,
+}
+
+impl Default for BlockRegistry {
+  fn default() -> Self {
+    return Self::builtin();
+  }
+}
+
+impl BlockRegistry {
+  /// Registry defined by the `res/blocks.json` shipped with the game.
+  pub fn builtin() -> Self {
+    return Self::from_json(include_str!("../../../res/blocks.json"))
+      .expect("Built-in block definitions are invalid");
+  }
+
+  pub fn from_json(json: &str) -> Result<Self> {
+    let definitions = serde_json::from_str::<Vec<BlockDefinition>>(json)?;
+    return Self::try_from(definitions.into_iter().map(BlockProperties::from).collect::<Vec<_>>());
+  }
+
+  pub fn get(&self, id: BlockId) -> Option<&BlockProperties> {
+    return self.blocks.get(id.index());
+  }
+
+  pub fn by_name(&self, name: &str) -> Option<BlockId> {
+    return self.names.get(name).copied();
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (BlockId, &BlockProperties)> {
+    return self.blocks.iter().enumerate().map(|(i, x)| (BlockId(i as u16), x));
+  }
+
+  pub fn len(&self) -> usize {
+    return self.blocks.len();
+  }
+
+  pub fn is_empty(&self) -> bool {
+    return self.blocks.is_empty();
+  }
+}
+
+impl TryFrom<Vec<BlockProperties>> for BlockRegistry {
+  type Error = anyhow::Error;
+
+  fn try_from(blocks: Vec<BlockProperties>) -> Result<Self> {
+    if blocks.first().map(|x| x.name.as_str()) != Some("air") {
+      return Err(anyhow!("The first block must be air"));
+    }
+
+    if blocks.len() > u16::MAX as usize {
+      return Err(anyhow!("Too many blocks: {}", blocks.len()));
+    }
+
+    let mut names = HashMap::with_capacity(blocks.len());
+    for (i, block) in blocks.iter().enumerate() {
+      if names.insert(block.name.clone(), BlockId(i as u16)).is_some() {
+        return Err(anyhow!("Block {} is defined more than once", block.name));
+      }
+    }
+
+    return Ok(Self { blocks, names });
+  }
+}
+
+impl From<BlockRegistry> for Vec<BlockProperties> {
+  fn from(registry: BlockRegistry) -> Self {
+    return registry.blocks;
+  }
+}
+
+// Human-friendly form of `BlockProperties` used in definition files.
+#[derive(Deserialize)]
+struct BlockDefinition {
+  name: String,
+  #[serde(default = "default_true")]
+  solid: bool,
+  #[serde(default = "default_true")]
+  opaque: bool,
+  #[serde(default)]
+  transparent: bool,
+  #[serde(default)]
+  textures: Option<TextureDefinition>,
+  #[serde(default)]
+  hardness: f32,
+  #[serde(default)]
+  light_emission: u8,
+}
+
+// More specific faces take precedence: `top` over `all`, `front` over `side` over `all`, etc.
+#[derive(Deserialize)]
+struct TextureDefinition {
+  all    : Option<String>,
+  side   : Option<String>,
+  top    : Option<String>,
+  bottom : Option<String>,
+  right  : Option<String>,
+  left   : Option<String>,
+  front  : Option<String>,
+  back   : Option<String>,
+}
+
+fn default_true() -> bool { true }
+
+impl From<BlockDefinition> for BlockProperties {
+  fn from(definition: BlockDefinition) -> Self {
+    let textures = definition.textures.map(|x| {
+      let all = x.all.unwrap_or_default();
+      let side = x.side.unwrap_or_else(|| all.clone());
+
+      BlockTextures {
+        top    : x.top.unwrap_or_else(|| all.clone()),
+        bottom : x.bottom.unwrap_or_else(|| all.clone()),
+        right  : x.right.unwrap_or_else(|| side.clone()),
+        left   : x.left.unwrap_or_else(|| side.clone()),
+        front  : x.front.unwrap_or_else(|| side.clone()),
+        back   : x.back.unwrap_or_else(|| side.clone()),
+      }
+    });
+
+    return Self {
+      name           : definition.name,
+      solid          : definition.solid,
+      opaque         : definition.opaque,
+      transparent    : definition.transparent,
+      textures,
+      hardness       : definition.hardness,
+      light_emission : definition.light_emission,
+    };
+  }
+}
@@ -1,29 +1,59 @@
 use glam::{IVec3, ivec3, Vec3};
 use serde::{Deserialize, Serialize};
 use crate::game::world::BlockId;
+use crate::game::world::biome::BiomeId;
+use crate::game::world::palette::PalettedContainer;
 
 pub const CHUNK_SIZE: usize = 32;
+pub const CHUNK_AREA: usize = CHUNK_SIZE * CHUNK_SIZE;
+pub const CHUNK_VOLUME: usize = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Chunk {
-  pub blocks: Vec<BlockId>,
+  pub blocks: PalettedContainer<BlockId, CHUNK_VOLUME>,
+  pub biomes: PalettedContainer<BiomeId, CHUNK_AREA>, // one per column
 }
 
 impl Default for Chunk {
   fn default() -> Self {
     return Self {
-      blocks: vec![BlockId::AIR; CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE],
+      blocks: PalettedContainer::new(BlockId::AIR),
+      biomes: PalettedContainer::new(BiomeId::PLAINS),
     };
   }
 }
 
 impl Chunk {
   pub fn get_block(&self, x: usize, y: usize, z: usize) -> BlockId {
-    return self.blocks[x + y * CHUNK_SIZE + z * CHUNK_SIZE * CHUNK_SIZE];
+    return self.blocks.get(x + y * CHUNK_SIZE + z * CHUNK_SIZE * CHUNK_SIZE);
   }
 
   pub fn set_block(&mut self, x: usize, y: usize, z: usize, block: BlockId) {
-    self.blocks[x + y * CHUNK_SIZE + z * CHUNK_SIZE * CHUNK_SIZE] = block;
+    self.blocks.set(x + y * CHUNK_SIZE + z * CHUNK_SIZE * CHUNK_SIZE, block);
+  }
+
+  pub fn get_biome(&self, x: usize, z: usize) -> BiomeId {
+    return self.biomes.get(x + z * CHUNK_SIZE);
+  }
+
+  pub fn set_biome(&mut self, x: usize, z: usize, biome: BiomeId) {
+    self.biomes.set(x + z * CHUNK_SIZE, biome);
+  }
+}
+
+pub trait ChunkIVec3Ext {
+  fn to_chunk_pos(&self) -> IVec3;
+  fn to_local_pos(&self) -> IVec3;
+}
+
+// Conversions for block positions in world space
+impl ChunkIVec3Ext for IVec3 {
+  fn to_chunk_pos(&self) -> IVec3 {
+    return self.div_euclid(IVec3::splat(CHUNK_SIZE as i32));
+  }
+
+  fn to_local_pos(&self) -> IVec3 {
+    return self.rem_euclid(IVec3::splat(CHUNK_SIZE as i32));
   }
 }
 
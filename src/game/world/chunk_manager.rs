@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use glam::IVec3;
-use crate::game::world::chunk::Chunk;
+use crate::game::world::BlockId;
+use crate::game::world::chunk::{Chunk, ChunkIVec3Ext};
 
 pub struct ChunkManager {
   pub chunks: HashMap<IVec3, Chunk>,
@@ -12,4 +13,22 @@ impl Default for ChunkManager {
       chunks: Default::default(),
     };
   }
+}
+
+impl ChunkManager {
+  pub fn get_block(&self, position: IVec3) -> Option<BlockId> {
+    let chunk = self.chunks.get(&position.to_chunk_pos())?;
+    let [x, y, z] = position.to_local_pos().to_array().map(|x| x as usize);
+
+    return Some(chunk.get_block(x, y, z));
+  }
+
+  /// Returns false if the chunk containing the block isn't loaded.
+  pub fn set_block(&mut self, position: IVec3, block: BlockId) -> bool {
+    let Some(chunk) = self.chunks.get_mut(&position.to_chunk_pos()) else { return false };
+    let [x, y, z] = position.to_local_pos().to_array().map(|x| x as usize);
+    chunk.set_block(x, y, z, block);
+
+    return true;
+  }
 }
\ No newline at end of file
@@ -3,13 +3,19 @@ use serde::{Deserialize, Serialize};
 pub mod world;
 pub mod chunk;
 pub mod chunk_manager;
+pub mod palette;
+pub mod block_registry;
+pub mod biome;
+pub mod raycast;
 pub mod worldgen;
 
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Ord, PartialOrd, Debug, Serialize, Deserialize)]
 pub struct BlockId(u16);
 
 impl BlockId {
-  pub const AIR   : BlockId = BlockId(0);
-  pub const TEST  : BlockId = BlockId(1);
-  pub const PANEL : BlockId = BlockId(2);
+  pub const AIR : BlockId = BlockId(0);
+
+  pub const fn index(self) -> usize {
+    return self.0 as usize;
+  }
 }
\ No newline at end of file
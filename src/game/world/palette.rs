@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize, Serializer};
+
+/// Fixed-size array of `LEN` values which stores only the distinct values it contains.
+///
+/// A container holding a single value doesn't allocate at all, otherwise every element is a
+/// bit-packed index into a palette of values. Indices don't span word boundaries, so a word
+/// holds `64 / bits` of them. Values which are no longer used are dropped from the palette before it
+/// would have to widen the indices, and whenever the container is serialized.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(try_from = "PalettedContainerData<T>", bound(deserialize = "T: Deserialize<'de> + Copy + Eq"))]
+pub enum PalettedContainer<T, const LEN: usize> {
+  Single(T),
+  Indirect {
+    palette : Vec<T>,
+    bits    : u8,
+    data    : Vec<u64>,
+  },
+}
+
+impl<T: Copy + Eq, const LEN: usize> PalettedContainer<T, LEN> {
+  pub fn new(value: T) -> Self {
+    return Self::Single(value);
+  }
+
+  pub fn get(&self, index: usize) -> T {
+    return match self {
+      Self::Single(value) => *value,
+      Self::Indirect { palette, bits, data } => palette[Self::read(data, *bits, index)],
+    };
+  }
+
+  pub fn set(&mut self, index: usize, value: T) {
+    let palette_index = match self {
+      Self::Single(current) if *current == value => return,
+      Self::Single(current) => {
+        *self = Self::indirect(*current);
+        None
+      }
+
+      Self::Indirect { palette, .. } => palette.iter().position(|x| *x == value),
+    };
+
+    let palette_index = palette_index.unwrap_or_else(|| self.insert(value));
+    let Self::Indirect { bits, data, .. } = self else { unreachable!() };
+    Self::write(data, *bits, index, palette_index);
+  }
+
+  pub fn fill(&mut self, value: T) {
+    *self = Self::Single(value);
+  }
+
+  /// Returns the value if the whole container is filled with it.
+  pub fn single(&self) -> Option<T> {
+    return match self {
+      Self::Single(value) => Some(*value),
+      Self::Indirect { .. } => None,
+    };
+  }
+
+  pub fn palette(&self) -> &[T] {
+    return match self {
+      Self::Single(value) => std::slice::from_ref(value),
+      Self::Indirect { palette, .. } => palette,
+    };
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+    return (0 .. LEN).map(|i| self.get(i));
+  }
+
+  /// Drops palette entries which are no longer used and shrinks the indices accordingly.
+  pub fn optimize(&mut self) {
+    let Some(used) = self.used() else { return };
+    let Self::Indirect { palette, bits, data } = self else { return };
+
+    let mut remap = vec![0; palette.len()];
+    let mut new_palette = Vec::with_capacity(palette.len());
+    for (i, value) in palette.iter().enumerate() {
+      if used[i] {
+        remap[i] = new_palette.len();
+        new_palette.push(*value);
+      }
+    }
+
+    if new_palette.len() == 1 {
+      *self = Self::Single(new_palette[0]);
+      return;
+    }
+
+    let new_bits = Self::bits_for(new_palette.len());
+    let mut new_data = vec![0; Self::words(new_bits)];
+    for i in 0 .. LEN {
+      Self::write(&mut new_data, new_bits, i, remap[Self::read(data, *bits, i)]);
+    }
+
+    *palette = new_palette;
+    *bits = new_bits;
+    *data = new_data;
+  }
+
+  /// Appends a value to the palette, widening the indices if they can't address it even without the unused values.
+  fn insert(&mut self, value: T) -> usize {
+    if let Self::Indirect { palette, bits, .. } = self {
+      if palette.len() == 1 << *bits {
+        self.optimize();
+      }
+    }
+
+    // all but one value may have been unused
+    if let Self::Single(current) = self {
+      *self = Self::indirect(*current);
+    }
+
+    let Self::Indirect { palette, bits, data } = self else { unreachable!() };
+
+    palette.push(value);
+    if palette.len() > 1 << *bits {
+      let new_bits = *bits + 1;
+      let mut new_data = vec![0; Self::words(new_bits)];
+      for i in 0 .. LEN {
+        Self::write(&mut new_data, new_bits, i, Self::read(data, *bits, i));
+      }
+
+      *bits = new_bits;
+      *data = new_data;
+    }
+
+    return palette.len() - 1;
+  }
+
+  fn indirect(value: T) -> Self {
+    return Self::Indirect {
+      palette : vec![value],
+      bits    : 1,
+      data    : vec![0; Self::words(1)],
+    };
+  }
+
+  // which palette entries are referenced by an index, `None` for a single value
+  fn used(&self) -> Option<Vec<bool>> {
+    let Self::Indirect { palette, bits, data } = self else { return None };
+
+    let mut used = vec![false; palette.len()];
+    for i in 0 .. LEN {
+      used[Self::read(data, *bits, i)] = true;
+    }
+
+    return Some(used);
+  }
+
+  fn read(data: &[u64], bits: u8, index: usize) -> usize {
+    let per_word = 64 / bits as usize;
+    let shift = (index % per_word) * bits as usize;
+    let mask = (1u64 << bits) - 1;
+
+    return ((data[index / per_word] >> shift) & mask) as usize;
+  }
+
+  fn write(data: &mut [u64], bits: u8, index: usize, value: usize) {
+    let per_word = 64 / bits as usize;
+    let shift = (index % per_word) * bits as usize;
+    let mask = (1u64 << bits) - 1;
+
+    let word = &mut data[index / per_word];
+    *word = (*word & !(mask << shift)) | ((value as u64 & mask) << shift);
+  }
+
+  fn words(bits: u8) -> usize {
+    return LEN.div_ceil(64 / bits as usize);
+  }
+
+  fn bits_for(palette_len: usize) -> u8 {
+    return (usize::BITS - (palette_len - 1).leading_zeros()).max(1) as u8;
+  }
+}
+
+impl<T: Serialize + Copy + Eq, const LEN: usize> Serialize for PalettedContainer<T, LEN> {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    // unused values would widen the indices on disk and on the wire
+    if self.used().is_some_and(|x| x.contains(&false)) {
+      let mut compact = self.clone();
+      compact.optimize();
+      return compact.serialize(serializer);
+    }
+
+    return match self {
+      Self::Single(value) => PalettedContainerRef::Single(value),
+      Self::Indirect { palette, bits, data } => PalettedContainerRef::Indirect { palette, bits: *bits, data },
+    }.serialize(serializer);
+  }
+}
+
+// Serialized form of the container, it has to match `PalettedContainerData`, borrowed to avoid copying it
+#[derive(Serialize)]
+enum PalettedContainerRef<'a, T> {
+  Single(&'a T),
+  Indirect {
+    palette : &'a [T],
+    bits    : u8,
+    data    : &'a [u64],
+  },
+}
+
+/// Unvalidated contents of a container, e.g. deserialized ones, which are checked before they can be indexed.
+#[derive(Deserialize, Debug)]
+pub enum PalettedContainerData<T> {
+  Single(T),
+  Indirect {
+    palette : Vec<T>,
+    bits    : u8,
+    data    : Vec<u64>,
+  },
+}
+
+impl<T: Copy + Eq, const LEN: usize> TryFrom<PalettedContainerData<T>> for PalettedContainer<T, LEN> {
+  type Error = String;
+
+  fn try_from(value: PalettedContainerData<T>) -> Result<Self, Self::Error> {
+    return match value {
+      PalettedContainerData::Single(value) => Ok(Self::Single(value)),
+      PalettedContainerData::Indirect { palette, bits, data } => {
+        if bits == 0 || bits > 16 || palette.is_empty() || palette.len() > 1 << bits {
+          return Err(format!("invalid palette of {} entries with {} bits per index", palette.len(), bits));
+        }
+
+        if data.len() != Self::words(bits) {
+          return Err(format!("expected {} words of palette indices, got {}", Self::words(bits), data.len()));
+        }
+
+        if (0 .. LEN).any(|i| Self::read(&data, bits, i) >= palette.len()) {
+          return Err(String::from("palette index out of bounds"));
+        }
+
+        Ok(Self::Indirect { palette, bits, data })
+      }
+    };
+  }
+}
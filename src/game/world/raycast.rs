@@ -0,0 +1,59 @@
+use glam::{IVec3, Vec3};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RaycastHit {
+  pub position : IVec3,
+  pub normal   : IVec3, // face of the block the ray entered through, zero if it started inside
+  pub distance : f32,
+}
+
+/// Walks the voxel grid along the ray until `is_hit` accepts a block (Amanatides & Woo).
+pub fn raycast(origin: Vec3, direction: Vec3, max_distance: f32, mut is_hit: impl FnMut(IVec3) -> bool) -> Option<RaycastHit> {
+  let direction = direction.try_normalize()?;
+
+  let mut position = origin.floor().as_ivec3();
+  let step = direction.signum().as_ivec3();
+
+  // distance along the ray between two grid crossings on each axis
+  let delta = (1.0 / direction).abs();
+
+  // distance along the ray to the first grid crossing on each axis
+  let mut next = Vec3::select(
+    direction.cmpgt(Vec3::ZERO),
+    (position.as_vec3() + 1.0 - origin) * delta,
+    (origin - position.as_vec3()) * delta,
+  );
+
+  // the ray never crosses the grid on axes it runs parallel to, zero times infinity would be NaN for origins on a grid line
+  next = Vec3::select(direction.cmpeq(Vec3::ZERO), Vec3::INFINITY, next);
+
+  let mut normal = IVec3::ZERO;
+  let mut distance = 0.0;
+  while distance <= max_distance {
+    if is_hit(position) {
+      return Some(RaycastHit { position, normal, distance });
+    }
+
+    let axis = if next.x < next.y && next.x < next.z { 0 } else if next.y < next.z { 1 } else { 2 };
+
+    distance = next[axis];
+    next[axis] += delta[axis];
+    position[axis] += step[axis];
+
+    normal = IVec3::ZERO;
+    normal[axis] = -step[axis];
+  }
+
+  return None;
+}
+
+/// The first block on a straight path `is_solid` accepts. The block the path starts in is ignored,
+/// so entities can leave blocks placed on top of them, and so are blocks the path only touches at its end.
+pub fn path_blocked(from: Vec3, to: Vec3, mut is_solid: impl FnMut(IVec3) -> bool) -> Option<IVec3> {
+  let start = from.floor().as_ivec3();
+  let length = from.distance(to);
+
+  return raycast(from, to - from, length, |position| position != start && is_solid(position))
+    .filter(|x| x.distance < length)
+    .map(|x| x.position);
+}
\ No newline at end of file
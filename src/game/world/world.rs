@@ -1,9 +1,12 @@
+use glam::IVec3;
 use crate::game::player::Player;
+use crate::game::world::block_registry::BlockRegistry;
 use crate::game::world::chunk_manager::ChunkManager;
 
 pub struct World {
   pub chunk_manager: ChunkManager,
   pub players: Vec<Player>,
+  pub registry: BlockRegistry,
 }
 
 impl Default for World {
@@ -11,6 +14,16 @@ impl Default for World {
     return Self {
       chunk_manager: ChunkManager::default(),
       players: vec![],
+      registry: BlockRegistry::default(),
     };
   }
+}
+
+impl World {
+  /// Blocks in chunks which aren't loaded aren't solid.
+  pub fn is_solid(&self, position: IVec3) -> bool {
+    return self.chunk_manager.get_block(position)
+      .and_then(|x| self.registry.get(x))
+      .is_some_and(|x| x.solid);
+  }
 }
\ No newline at end of file
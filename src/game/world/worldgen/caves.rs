@@ -0,0 +1,129 @@
+use std::f64::consts::PI;
+use glam::{dvec3, DVec3, IVec3};
+use crate::game::world::chunk::CHUNK_SIZE;
+use crate::game::world::worldgen::noise::FractalNoise;
+use crate::game::world::worldgen::random::WorldRandom;
+
+// Cheese caves only appear this far below the surface, so the ground isn't riddled with holes
+const CHEESE_MIN_DEPTH : i32 = 8;
+const CHEESE_THRESHOLD : f64 = 0.32;
+
+const WORM_SALT        : u64 = 0xCA7E_0000_0000_0001;
+const WORM_CHANCE      : f64 = 0.3; // chance of a chunk to start worms
+const WORM_MAX_COUNT   : i32 = 3;
+const WORM_MAX_LENGTH  : i32 = 112; // in steps of one block
+const WORM_MAX_RADIUS  : f64 = 4.0;
+const WORM_MAX_START_Y : i32 = 64; // worms only start below this height
+
+// Farthest distance a worm can carve away from the chunk it started in
+const WORM_REACH: i32 = WORM_MAX_LENGTH + WORM_MAX_RADIUS as i32 + 1;
+
+/// Carves caves out of generated terrain.
+///
+/// Cheese caves are large open areas where 3D noise exceeds a threshold. Worms are tunnels
+/// following a random walk from a starting point in some chunk, they may reach far into
+/// neighbouring chunks. A chunk replays every worm started within reach of it and only
+/// keeps the blocks inside of its own bounds, so tunnels line up no matter which chunk
+/// is generated first.
+pub struct CaveCarver {
+  seed   : u64,
+  cheese : FractalNoise,
+}
+
+impl CaveCarver {
+  pub fn new(seed: u64) -> Self {
+    return Self {
+      seed,
+      cheese: FractalNoise::new(seed ^ 0xC4EE_5E00_0000_0003, 3, 1.0 / 64.0),
+    };
+  }
+
+  /// Whether a block `depth` blocks below the surface is hollowed out by a cheese cave.
+  pub fn is_cheese_cave(&self, position: IVec3, depth: i32) -> bool {
+    if depth < CHEESE_MIN_DEPTH {
+      return false;
+    }
+
+    // squashed vertically, so caves are wider than they are high
+    let value = self.cheese.sample_3d(position.x as f64, position.y as f64 * 2.0, position.z as f64);
+    return value > CHEESE_THRESHOLD;
+  }
+
+  /// Calls `carve` for every block in `min .. max` which is removed by a worm, blocks may be visited more than once.
+  pub fn carve_worms(&self, min: IVec3, max: IVec3, mut carve: impl FnMut(IVec3)) {
+    let chunk_size = IVec3::splat(CHUNK_SIZE as i32);
+    let first = (min - WORM_REACH).div_euclid(chunk_size);
+    let last = (max - 1 + WORM_REACH).div_euclid(chunk_size);
+
+    for x in first.x ..= last.x {
+      for y in first.y ..= last.y.min((WORM_MAX_START_Y - 1).div_euclid(CHUNK_SIZE as i32)) {
+        for z in first.z ..= last.z {
+          let chunk_pos = IVec3::new(x, y, z);
+          let mut random = WorldRandom::at(self.seed, chunk_pos, WORM_SALT);
+          if !random.chance(WORM_CHANCE) {
+            continue;
+          }
+
+          for _ in 0 .. random.range(1 .. WORM_MAX_COUNT + 1) {
+            self.carve_worm(&mut random, chunk_pos, min, max, &mut carve);
+          }
+        }
+      }
+    }
+  }
+
+  fn carve_worm(&self, random: &mut WorldRandom, chunk_pos: IVec3, min: IVec3, max: IVec3, carve: &mut impl FnMut(IVec3)) {
+    // every random value is drawn before the early exit, the next worm of the chunk must not depend on it
+    let origin = chunk_pos * CHUNK_SIZE as i32;
+    let mut position = dvec3(
+      (origin.x + random.range(0 .. CHUNK_SIZE as i32)) as f64,
+      (origin.y + random.range(0 .. CHUNK_SIZE as i32)) as f64,
+      (origin.z + random.range(0 .. CHUNK_SIZE as i32)) as f64,
+    );
+
+    let length = random.range(WORM_MAX_LENGTH / 2 .. WORM_MAX_LENGTH + 1);
+    let width = 1.0 + random.next_f64() * (WORM_MAX_RADIUS - 1.5);
+    let mut yaw = random.next_f64() * PI * 2.0;
+    let mut pitch = (random.next_f64() - 0.5) * 0.5;
+    let mut worm_random = WorldRandom::new(random.next_u64());
+
+    // worms can't reach the area at all
+    let start = position.as_ivec3();
+    if (start + WORM_REACH).cmplt(min).any() || (start - WORM_REACH).cmpge(max).any() {
+      return;
+    }
+
+    let mut yaw_change = 0.0;
+    let mut pitch_change = 0.0;
+
+    for step in 0 .. length {
+      // thickest in the middle, tapering off towards both ends
+      let radius = 1.5 + (step as f64 * PI / length as f64).sin() * width;
+      Self::carve_sphere(position, radius, min, max, carve);
+
+      position += dvec3(yaw.cos() * pitch.cos(), pitch.sin(), yaw.sin() * pitch.cos());
+
+      // smoothed random turns, pitch is pulled back towards horizontal
+      yaw += yaw_change * 0.1;
+      pitch = pitch * 0.7 + pitch_change * 0.1;
+      yaw_change = yaw_change * 0.75 + (worm_random.next_f64() - 0.5) * 2.0;
+      pitch_change = pitch_change * 0.9 + (worm_random.next_f64() - 0.5) * 1.0;
+    }
+  }
+
+  fn carve_sphere(center: DVec3, radius: f64, min: IVec3, max: IVec3, carve: &mut impl FnMut(IVec3)) {
+    let low = (center - radius).floor().as_ivec3().max(min);
+    let high = (center + radius).ceil().as_ivec3().min(max);
+
+    for x in low.x .. high.x {
+      for y in low.y .. high.y {
+        for z in low.z .. high.z {
+          let block = IVec3::new(x, y, z);
+          if (block.as_dvec3() + 0.5).distance_squared(center) < radius * radius {
+            carve(block);
+          }
+        }
+      }
+    }
+  }
+}
\ No newline at end of file
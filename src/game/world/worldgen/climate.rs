@@ -0,0 +1,69 @@
+use crate::game::world::biome::BiomeId;
+use crate::game::world::worldgen::noise::FractalNoise;
+
+// Controls how wide the transitions between biomes are, smaller values give sharper borders
+const BLEND_SHARPNESS: f64 = 0.01;
+
+/// Biome parameters of a single column, interpolated between neighbouring biomes.
+#[derive(Debug, Clone, Copy)]
+pub struct ColumnClimate {
+  pub biome              : BiomeId, // closest biome on the climate map
+  pub base_height        : f64,
+  pub height_amplitude   : f64,
+  pub vegetation_density : f64,
+}
+
+/// Temperature and humidity fields which select the biome of every column.
+pub struct ClimateMap {
+  temperature : FractalNoise,
+  humidity    : FractalNoise,
+}
+
+impl ClimateMap {
+  pub fn new(seed: u64) -> Self {
+    return Self {
+      temperature : FractalNoise::new(seed ^ 0x7E3A_0000_0000_0001, 4, 1.0 / 1024.0),
+      humidity    : FractalNoise::new(seed ^ 0x4E1D_0000_0000_0002, 4, 1.0 / 1024.0),
+    };
+  }
+
+  pub fn sample(&self, x: i32, z: i32) -> ColumnClimate {
+    // fractal noise rarely leaves [-0.5, 0.5], stretch it to cover the whole climate map
+    let temperature = (self.temperature.sample_2d(x as f64, z as f64) + 0.5).clamp(0.0, 1.0);
+    let humidity = (self.humidity.sample_2d(x as f64, z as f64) + 0.5).clamp(0.0, 1.0);
+
+    let mut biome = BiomeId::PLAINS;
+    let mut closest = f64::MAX;
+
+    let mut total = 0.0;
+    let mut base_height = 0.0;
+    let mut height_amplitude = 0.0;
+    let mut vegetation_density = 0.0;
+
+    for id in BiomeId::iter() {
+      let parameters = id.get();
+      let distance
+        = (parameters.temperature - temperature).powi(2)
+        + (parameters.humidity - humidity).powi(2);
+
+      if distance < closest {
+        closest = distance;
+        biome = id;
+      }
+
+      // weights fall off quickly with the distance, so only nearby biomes contribute
+      let weight = (-distance / BLEND_SHARPNESS).exp();
+      total += weight;
+      base_height += parameters.base_height * weight;
+      height_amplitude += parameters.height_amplitude * weight;
+      vegetation_density += parameters.vegetation_density * weight;
+    }
+
+    return ColumnClimate {
+      biome,
+      base_height        : base_height / total,
+      height_amplitude   : height_amplitude / total,
+      vegetation_density : vegetation_density / total,
+    };
+  }
+}
\ No newline at end of file
@@ -0,0 +1,386 @@
+use glam::{ivec3, DVec3, IVec2, IVec3};
+use crate::game::world::BlockId;
+use crate::game::world::biome::BiomeId;
+use crate::game::world::block_registry::BlockRegistry;
+use crate::game::world::chunk::{Chunk, CHUNK_AREA, CHUNK_SIZE};
+use crate::game::world::worldgen::random::WorldRandom;
+use crate::game::world::worldgen::worldgen::WorldGen;
+
+// Farthest distance a feature can extend away from the chunk it's placed in
+const FEATURE_REACH: i32 = 12;
+
+const ORE_SALT     : u64 = 0x0DE5_0000_0000_0001;
+const SURFACE_SALT : u64 = 0x5EED_0000_0000_0002;
+
+// Vegetation density is scaled so a column with this density gets one attempt on average
+const MAX_VEGETATION_DENSITY: f64 = 0.025;
+const TREE_ATTEMPTS: i32 = (CHUNK_AREA as f64 * MAX_VEGETATION_DENSITY) as i32;
+
+const BOULDER_CHANCE : f64 = 0.15;
+const PREFAB_CHANCE  : f64 = 0.02;
+
+// Prefabs are only placed on flat ground, with a limited foundation below them
+const PREFAB_MAX_SLOPE      : i32 = 2;
+const PREFAB_MAX_FOUNDATION : i32 = 4;
+
+struct OreKind {
+  name     : &'static str,
+  attempts : i32, // veins per chunk
+  max_y    : i32,
+  size     : i32, // blocks visited by the random walk of a vein
+}
+
+const ORES: &[OreKind] = &[
+  OreKind { name: "coal_ore", attempts: 10, max_y: 64, size: 12 },
+  OreKind { name: "iron_ore", attempts: 6,  max_y: 16, size: 8  },
+];
+
+/// Small hand-made structure, `layers` go from the ground upwards and are indexed by `[y][z]`, characters by x.
+///
+/// Spaces keep the existing block, every other character is looked up in `legend`.
+struct Prefab {
+  legend : &'static [(char, &'static str)],
+  layers : &'static [&'static [&'static str]],
+}
+
+const PREFABS: &[Prefab] = &[
+  // well with a roof on four posts
+  Prefab {
+    legend : &[('.', "air"), ('c', "cobblestone"), ('p', "planks")],
+    layers : &[
+      &["ccccc", "ccccc", "cc.cc", "ccccc", "ccccc"],
+      &[".....", ".ccc.", ".c.c.", ".ccc.", "....."],
+      &[".....", ".p.p.", ".....", ".p.p.", "....."],
+      &[".....", ".p.p.", ".....", ".p.p.", "....."],
+      &["     ", " ppp ", " ppp ", " ppp ", "     "],
+    ],
+  },
+  // remains of a small house
+  Prefab {
+    legend : &[('.', "air"), ('c', "cobblestone"), ('s', "stone"), ('p', "planks")],
+    layers : &[
+      &["ccccccc", "cssssss", "csssssc", "cssspsc", "csssssc", "csssssc", "ccccccc"],
+      &["ccc.ccc", "c......", "c.....c", "c.....c", "c.....c", "c.....c", "cc.cccc"],
+      &["cc...cc", "c......", "c.....c", ".......", "c......", "c.....c", "c..ccc."],
+      &["c.....c", ".......", ".......", ".......", ".......", ".......", "...c..."],
+    ],
+  },
+];
+
+/// Writes blocks of features into a single chunk, blocks outside of it are dropped.
+///
+/// Features are placed in world space and replayed by every chunk they reach, so writes must
+/// only depend on the feature itself and on blocks of the chunk being written to.
+pub struct ChunkWriter<'a> {
+  chunk  : &'a mut Chunk,
+  origin : IVec3,
+}
+
+impl<'a> ChunkWriter<'a> {
+  pub fn new(chunk: &'a mut Chunk, chunk_pos: IVec3) -> Self {
+    return Self {
+      chunk,
+      origin: chunk_pos * CHUNK_SIZE as i32,
+    };
+  }
+
+  pub fn get(&self, position: IVec3) -> Option<BlockId> {
+    let local = self.local(position)?;
+    return Some(self.chunk.get_block(local.x as usize, local.y as usize, local.z as usize));
+  }
+
+  pub fn set(&mut self, position: IVec3, block: BlockId) {
+    if let Some(local) = self.local(position) {
+      self.chunk.set_block(local.x as usize, local.y as usize, local.z as usize, block);
+    }
+  }
+
+  /// Sets the block only if `predicate` accepts the block currently there.
+  pub fn replace(&mut self, position: IVec3, block: BlockId, predicate: impl Fn(BlockId) -> bool) {
+    if self.get(position).is_some_and(predicate) {
+      self.set(position, block);
+    }
+  }
+
+  fn local(&self, position: IVec3) -> Option<IVec3> {
+    let local = position - self.origin;
+    return (local.cmpge(IVec3::ZERO).all() && local.cmplt(IVec3::splat(CHUNK_SIZE as i32)).all()).then_some(local);
+  }
+}
+
+// Blocks which features are built from
+struct FeatureBlocks {
+  stone       : BlockId,
+  log         : BlockId,
+  leaves      : BlockId,
+  cobblestone : BlockId,
+  ores        : Vec<BlockId>, // indexed like `ORES`
+  prefabs     : Vec<Vec<(char, BlockId)>>, // legends of `PREFABS`
+}
+
+/// Second stage of world generation which places trees, boulders, ore veins and prefabs on top of the terrain.
+///
+/// Features are planned per chunk from a generator seeded with the chunk position, a chunk
+/// being decorated plans the features of all chunks within reach and writes the parts which
+/// fall into its own bounds. Planning only looks at the deterministic terrain functions of
+/// `WorldGen`, never at blocks of other chunks, so neighbours agree on every feature no
+/// matter in which order they're generated.
+pub struct Decorator {
+  seed   : u64,
+  blocks : FeatureBlocks,
+}
+
+impl Decorator {
+  pub fn new(registry: &BlockRegistry, seed: u64) -> Self {
+    let block = |name: &str| registry.by_name(name)
+      .unwrap_or_else(|| panic!("Block registry has no {} block", name));
+
+    let prefabs = PREFABS.iter()
+      .map(|x| x.legend.iter().map(|(c, name)| (*c, block(name))).collect())
+      .collect();
+
+    return Self {
+      seed,
+      blocks: FeatureBlocks {
+        stone       : block("stone"),
+        log         : block("log"),
+        leaves      : block("leaves"),
+        cobblestone : block("cobblestone"),
+        ores        : ORES.iter().map(|x| block(x.name)).collect(),
+        prefabs,
+      },
+    };
+  }
+
+  pub fn decorate(&self, worldgen: &WorldGen, chunk_pos: IVec3, chunk: &mut Chunk) {
+    let mut writer = ChunkWriter::new(chunk, chunk_pos);
+    let radius = (FEATURE_REACH + CHUNK_SIZE as i32 - 1) / CHUNK_SIZE as i32;
+
+    // every chunk must place overlapping features in the same order, ores go first so surface features aren't replaced
+    for x in -radius ..= radius {
+      for y in -radius ..= radius {
+        for z in -radius ..= radius {
+          self.place_ores(chunk_pos + ivec3(x, y, z), &mut writer);
+        }
+      }
+    }
+
+    for x in -radius ..= radius {
+      for z in -radius ..= radius {
+        self.place_surface_features(worldgen, IVec2::new(chunk_pos.x + x, chunk_pos.z + z), chunk_pos.y, &mut writer);
+      }
+    }
+  }
+
+  fn place_ores(&self, chunk_pos: IVec3, writer: &mut ChunkWriter) {
+    let origin = chunk_pos * CHUNK_SIZE as i32;
+    let mut random = WorldRandom::at(self.seed, chunk_pos, ORE_SALT);
+
+    for (ore, block) in ORES.iter().zip(&self.blocks.ores) {
+      if origin.y >= ore.max_y {
+        continue;
+      }
+
+      for _ in 0 .. ore.attempts {
+        let start = origin + ivec3(
+          random.range(0 .. CHUNK_SIZE as i32),
+          random.range(0 .. CHUNK_SIZE as i32),
+          random.range(0 .. CHUNK_SIZE as i32),
+        );
+
+        let mut vein_random = WorldRandom::new(random.next_u64());
+        if start.y < ore.max_y {
+          self.place_ore_vein(start, ore.size, *block, &mut vein_random, writer);
+        }
+      }
+    }
+  }
+
+  // Surface features of a column of chunks, `chunk_y` is the height of the chunk being decorated
+  fn place_surface_features(&self, worldgen: &WorldGen, column: IVec2, chunk_y: i32, writer: &mut ChunkWriter) {
+    let origin = column * CHUNK_SIZE as i32;
+    let mut random = WorldRandom::at(self.seed, ivec3(column.x, 0, column.y), SURFACE_SALT);
+    let column_at = |random: &mut WorldRandom| IVec2::new(
+      origin.x + random.range(0 .. CHUNK_SIZE as i32),
+      origin.y + random.range(0 .. CHUNK_SIZE as i32),
+    );
+
+    // everything is drawn up front, so the plan doesn't depend on the terrain checks below
+    let boulder = random.chance(BOULDER_CHANCE).then(|| (column_at(&mut random), random.next_u64()));
+    let prefab = random.chance(PREFAB_CHANCE).then(|| (column_at(&mut random), random.next_u64()));
+    let trees = (0 .. TREE_ATTEMPTS).map(|_| (column_at(&mut random), random.next_f64(), random.next_u64())).collect::<Vec<_>>();
+
+    // features on a surface at this height can't reach the chunk being decorated
+    let min_height = chunk_y * CHUNK_SIZE as i32 - FEATURE_REACH;
+    let max_height = (chunk_y + 1) * CHUNK_SIZE as i32 + PREFAB_MAX_FOUNDATION + 1;
+    let in_range = |height: i32| height >= min_height && height <= max_height;
+
+    if let Some((position, seed)) = boulder {
+      let climate = worldgen.climate(position.x, position.y);
+      let height = worldgen.height(position.x, position.y);
+      if in_range(height) && matches!(climate.biome, BiomeId::MOUNTAINS | BiomeId::PLAINS | BiomeId::TUNDRA) {
+        let mut random = WorldRandom::new(seed);
+        self.place_boulder(ivec3(position.x, height, position.y), &mut random, writer);
+      }
+    }
+
+    for (position, roll, seed) in trees {
+      let climate = worldgen.climate(position.x, position.y);
+      if roll * MAX_VEGETATION_DENSITY >= climate.vegetation_density {
+        continue;
+      }
+
+      let height = worldgen.height(position.x, position.y);
+      if !in_range(height) {
+        continue;
+      }
+
+      let mut random = WorldRandom::new(seed);
+      let base = ivec3(position.x, height, position.y);
+      match climate.biome {
+        BiomeId::TUNDRA | BiomeId::MOUNTAINS => self.place_spruce(base, &mut random, writer),
+        BiomeId::DESERT => {}
+        _ => self.place_tree(base, &mut random, writer),
+      }
+    }
+
+    if let Some((position, seed)) = prefab {
+      let climate = worldgen.climate(position.x, position.y);
+      if climate.biome != BiomeId::MOUNTAINS {
+        let mut random = WorldRandom::new(seed);
+        self.place_prefab(worldgen, position, &mut random, writer, &in_range);
+      }
+    }
+  }
+
+  fn place_ore_vein(&self, start: IVec3, size: i32, ore: BlockId, random: &mut WorldRandom, writer: &mut ChunkWriter) {
+    let stone = self.blocks.stone;
+    let mut position = start;
+
+    for _ in 0 .. size {
+      writer.replace(position, ore, |x| x == stone);
+      position += ivec3(random.range(-1 .. 2), random.range(-1 .. 2), random.range(-1 .. 2));
+    }
+  }
+
+  fn place_boulder(&self, base: IVec3, random: &mut WorldRandom, writer: &mut ChunkWriter) {
+    let radius = 1.2 + random.next_f64() * 1.3;
+    let center = base.as_dvec3() + 0.5 - DVec3::Y;
+    let extent = radius.ceil() as i32;
+
+    for x in -extent ..= extent {
+      for y in -extent ..= extent {
+        for z in -extent ..= extent {
+          let position = base - IVec3::Y + ivec3(x, y, z);
+          let distance = (position.as_dvec3() + 0.5).distance(center);
+          if distance < radius {
+            let block = if random.chance(0.7) { self.blocks.cobblestone } else { self.blocks.stone };
+            writer.set(position, block);
+          }
+        }
+      }
+    }
+  }
+
+  fn place_tree(&self, base: IVec3, random: &mut WorldRandom, writer: &mut ChunkWriter) {
+    let trunk = random.range(4 .. 7);
+    let top = base.y + trunk;
+    let air = BlockId::AIR;
+
+    // two wide layers with ragged corners and two narrow ones at the top
+    for y in top - 2 ..= top + 1 {
+      let radius: i32 = if y < top { 2 } else { 1 };
+      for x in -radius ..= radius {
+        for z in -radius ..= radius {
+          let corner = x.abs() == radius && z.abs() == radius;
+          if corner && (y == top + 1 || random.chance(0.5)) {
+            continue;
+          }
+
+          writer.replace(ivec3(base.x + x, y, base.z + z), self.blocks.leaves, |x| x == air);
+        }
+      }
+    }
+
+    self.place_trunk(base, trunk, writer);
+  }
+
+  fn place_spruce(&self, base: IVec3, random: &mut WorldRandom, writer: &mut ChunkWriter) {
+    let trunk = random.range(6 .. 9);
+    let top = base.y + trunk;
+    let air = BlockId::AIR;
+
+    // cone of alternating wide and narrow layers
+    for y in base.y + 2 ..= top + 1 {
+      let radius = match top + 1 - y {
+        0 => 0,
+        x if x % 2 == 1 => 1,
+        x => (1 + x / 3).min(3),
+      };
+
+      for x in -radius ..= radius {
+        for z in -radius ..= radius {
+          if x.abs() + z.abs() <= radius {
+            writer.replace(ivec3(base.x + x, y, base.z + z), self.blocks.leaves, |x| x == air);
+          }
+        }
+      }
+    }
+
+    self.place_trunk(base, trunk, writer);
+  }
+
+  fn place_trunk(&self, base: IVec3, height: i32, writer: &mut ChunkWriter) {
+    let (air, leaves) = (BlockId::AIR, self.blocks.leaves);
+    for y in 0 .. height {
+      writer.replace(base + ivec3(0, y, 0), self.blocks.log, |x| x == air || x == leaves);
+    }
+  }
+
+  fn place_prefab(&self, worldgen: &WorldGen, corner: IVec2, random: &mut WorldRandom, writer: &mut ChunkWriter, in_range: &impl Fn(i32) -> bool) {
+    let index = random.range(0 .. PREFABS.len() as i32) as usize;
+    let rotation = random.range(0 .. 4);
+    let (prefab, legend) = (&PREFABS[index], &self.blocks.prefabs[index]);
+    let size = prefab.layers[0].len() as i32;
+
+    // footprint in world space after rotating the prefab around its corner
+    let rotate = |x: i32, z: i32| match rotation {
+      0 => IVec2::new(x, z),
+      1 => IVec2::new(size - 1 - z, x),
+      2 => IVec2::new(size - 1 - x, size - 1 - z),
+      _ => IVec2::new(z, size - 1 - x),
+    };
+
+    let heights = (0 .. size)
+      .flat_map(|z| (0 .. size).map(move |x| (x, z)))
+      .map(|(x, z)| {
+        let column = corner + IVec2::new(x, z);
+        (column, worldgen.height(column.x, column.y))
+      })
+      .collect::<Vec<_>>();
+
+    let lowest = heights.iter().map(|x| x.1).min().unwrap_or(0);
+    let highest = heights.iter().map(|x| x.1).max().unwrap_or(0);
+    if highest - lowest > PREFAB_MAX_SLOPE || !in_range(highest) {
+      return;
+    }
+
+    // the first layer replaces the surface, gaps below are filled up to it
+    let ground = highest - 1;
+    for (column, height) in &heights {
+      for y in (*height).max(ground - PREFAB_MAX_FOUNDATION) .. ground {
+        writer.set(ivec3(column.x, y, column.y), self.blocks.cobblestone);
+      }
+    }
+
+    for (y, layer) in prefab.layers.iter().enumerate() {
+      for (z, row) in layer.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+          let Some((_, block)) = legend.iter().find(|(key, _)| *key == c) else { continue };
+          let column = corner + rotate(x as i32, z as i32);
+          writer.set(ivec3(column.x, ground + y as i32, column.y), *block);
+        }
+      }
+    }
+  }
+}
\ No newline at end of file
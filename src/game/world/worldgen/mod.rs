@@ -1 +1,6 @@
-pub mod worldgen;
\ No newline at end of file
+pub mod worldgen;
+pub mod noise;
+pub mod random;
+pub mod climate;
+pub mod caves;
+pub mod features;
\ No newline at end of file
@@ -0,0 +1,130 @@
+use glam::{ivec3, IVec3};
+use crate::game::world::worldgen::random::WorldRandom;
+
+/// Seeded gradient noise (improved Perlin noise), values are roughly within [-1, 1].
+///
+/// Gradients are picked by hashing the whole lattice coordinate with the seed, so the noise doesn't repeat
+/// like it would with a permutation table.
+#[derive(Clone)]
+pub struct GradientNoise {
+  seed: u64,
+}
+
+impl GradientNoise {
+  pub fn new(seed: u64) -> Self {
+    return Self { seed };
+  }
+
+  pub fn sample_2d(&self, x: f64, y: f64) -> f64 {
+    let (xi, xf) = Self::split(x);
+    let (yi, yf) = Self::split(y);
+    let (u, v) = (Self::fade(xf), Self::fade(yf));
+
+    let hash = |dx, dy| self.hash(ivec3(xi + dx, yi + dy, 0));
+
+    return Self::lerp(v,
+      Self::lerp(u, Self::grad_2d(hash(0, 0), xf, yf), Self::grad_2d(hash(1, 0), xf - 1.0, yf)),
+      Self::lerp(u, Self::grad_2d(hash(0, 1), xf, yf - 1.0), Self::grad_2d(hash(1, 1), xf - 1.0, yf - 1.0)),
+    );
+  }
+
+  pub fn sample_3d(&self, x: f64, y: f64, z: f64) -> f64 {
+    let (xi, xf) = Self::split(x);
+    let (yi, yf) = Self::split(y);
+    let (zi, zf) = Self::split(z);
+    let (u, v, w) = (Self::fade(xf), Self::fade(yf), Self::fade(zf));
+
+    let hash = |dx, dy, dz| self.hash(ivec3(xi + dx, yi + dy, zi + dz));
+
+    return Self::lerp(w,
+      Self::lerp(v,
+        Self::lerp(u, Self::grad_3d(hash(0, 0, 0), xf, yf, zf), Self::grad_3d(hash(1, 0, 0), xf - 1.0, yf, zf)),
+        Self::lerp(u, Self::grad_3d(hash(0, 1, 0), xf, yf - 1.0, zf), Self::grad_3d(hash(1, 1, 0), xf - 1.0, yf - 1.0, zf)),
+      ),
+      Self::lerp(v,
+        Self::lerp(u, Self::grad_3d(hash(0, 0, 1), xf, yf, zf - 1.0), Self::grad_3d(hash(1, 0, 1), xf - 1.0, yf, zf - 1.0)),
+        Self::lerp(u, Self::grad_3d(hash(0, 1, 1), xf, yf - 1.0, zf - 1.0), Self::grad_3d(hash(1, 1, 1), xf - 1.0, yf - 1.0, zf - 1.0)),
+      ),
+    );
+  }
+
+  // Picks the gradient of a lattice point
+  fn hash(&self, lattice: IVec3) -> u8 {
+    return WorldRandom::at(self.seed, lattice, 0).next_u64() as u8;
+  }
+
+  // Splits a coordinate into the lattice cell and the offset within it
+  fn split(x: f64) -> (i32, f64) {
+    let floor = x.floor();
+    return (floor as i32, x - floor);
+  }
+
+  fn fade(t: f64) -> f64 {
+    return t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+  }
+
+  fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    return a + t * (b - a);
+  }
+
+  fn grad_2d(hash: u8, x: f64, y: f64) -> f64 {
+    return match hash & 7 {
+      0 =>  x + y, 1 =>  x - y, 2 => -x + y, 3 => -x - y,
+      4 =>  x,     5 => -x,     6 =>  y,     _ => -y,
+    };
+  }
+
+  fn grad_3d(hash: u8, x: f64, y: f64, z: f64) -> f64 {
+    return match hash & 15 {
+      0  =>  x + y, 1  => -x + y, 2  =>  x - y, 3  => -x - y,
+      4  =>  x + z, 5  => -x + z, 6  =>  x - z, 7  => -x - z,
+      8  =>  y + z, 9  => -y + z, 10 =>  y - z, 11 => -y - z,
+      12 =>  x + y, 13 => -y + z, 14 => -x + y, _  => -y - z,
+    };
+  }
+}
+
+/// Several octaves of gradient noise layered on top of each other, normalized to roughly [-1, 1].
+#[derive(Clone)]
+pub struct FractalNoise {
+  octaves     : Vec<GradientNoise>,
+  frequency   : f64,
+  lacunarity  : f64, // frequency multiplier between octaves
+  persistence : f64, // amplitude multiplier between octaves
+}
+
+impl FractalNoise {
+  pub fn new(seed: u64, octaves: usize, frequency: f64) -> Self {
+    let mut random = WorldRandom::new(seed);
+    return Self {
+      octaves     : (0 .. octaves).map(|_| GradientNoise::new(random.next_u64())).collect(),
+      frequency,
+      lacunarity  : 2.0,
+      persistence : 0.5,
+    };
+  }
+
+  pub fn sample_2d(&self, x: f64, y: f64) -> f64 {
+    return self.layer(|noise, frequency| noise.sample_2d(x * frequency, y * frequency));
+  }
+
+  pub fn sample_3d(&self, x: f64, y: f64, z: f64) -> f64 {
+    return self.layer(|noise, frequency| noise.sample_3d(x * frequency, y * frequency, z * frequency));
+  }
+
+  fn layer(&self, sample: impl Fn(&GradientNoise, f64) -> f64) -> f64 {
+    let mut value = 0.0;
+    let mut total = 0.0;
+    let mut frequency = self.frequency;
+    let mut amplitude = 1.0;
+
+    for noise in &self.octaves {
+      value += sample(noise, frequency) * amplitude;
+      total += amplitude;
+      frequency *= self.lacunarity;
+      amplitude *= self.persistence;
+    }
+
+    return if total > 0.0 { value / total } else { 0.0 };
+  }
+}
\ No newline at end of file
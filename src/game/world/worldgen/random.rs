@@ -0,0 +1,48 @@
+use std::ops::Range;
+use glam::IVec3;
+
+/// Small deterministic generator (SplitMix64), world generation must be reproducible on every platform.
+#[derive(Clone, Debug)]
+pub struct WorldRandom {
+  state: u64,
+}
+
+impl WorldRandom {
+  pub fn new(seed: u64) -> Self {
+    return Self { state: seed };
+  }
+
+  /// Generator derived from the world seed and a position, `salt` separates independent uses of the same position.
+  pub fn at(seed: u64, position: IVec3, salt: u64) -> Self {
+    let mut random = Self::new(seed ^ salt.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    for coordinate in position.to_array() {
+      random.state ^= coordinate as u32 as u64;
+      random.next_u64();
+    }
+
+    return random;
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+    let mut z = self.state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    return z ^ (z >> 31);
+  }
+
+  // Uniformly distributed in [0, 1)
+  pub fn next_f64(&mut self) -> f64 {
+    return (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+  }
+
+  pub fn range(&mut self, range: Range<i32>) -> i32 {
+    let length = (range.end - range.start).max(1) as u64;
+    return range.start + (self.next_u64() % length) as i32;
+  }
+
+  pub fn chance(&mut self, probability: f64) -> bool {
+    return self.next_f64() < probability;
+  }
+}
\ No newline at end of file
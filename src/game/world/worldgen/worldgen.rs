@@ -1,33 +1,116 @@
 use glam::IVec3;
 use crate::game::world::BlockId;
+use crate::game::world::biome::BIOMES;
+use crate::game::world::block_registry::BlockRegistry;
 use crate::game::world::chunk::{Chunk, CHUNK_SIZE};
+use crate::game::world::worldgen::caves::CaveCarver;
+use crate::game::world::worldgen::features::Decorator;
+use crate::game::world::worldgen::climate::{ClimateMap, ColumnClimate};
+use crate::game::world::worldgen::noise::FractalNoise;
+
+const FILLER_DEPTH: i32 = 3;
+
+// Blocks making up the surface of a biome
+struct BiomeBlocks {
+  surface : BlockId,
+  filler  : BlockId,
+}
 
 pub struct WorldGen {
+  seed         : u64,
+  height_noise : FractalNoise,
+  climate      : ClimateMap,
+  caves        : CaveCarver,
+  decorator    : Decorator,
 
+  stone  : BlockId,
+  biomes : Vec<BiomeBlocks>, // indexed by biome
 }
 
 impl WorldGen {
+  pub fn new(registry: &BlockRegistry, seed: u64) -> Self {
+    let block = |name: &str| registry.by_name(name)
+      .unwrap_or_else(|| panic!("Block registry has no {} block", name));
+
+    let biomes = BIOMES.iter()
+      .map(|x| BiomeBlocks {
+        surface : block(x.surface),
+        filler  : block(x.filler),
+      })
+      .collect();
+
+    return Self {
+      seed,
+      height_noise : FractalNoise::new(seed, 5, 1.0 / 256.0),
+      climate      : ClimateMap::new(seed),
+      caves        : CaveCarver::new(seed),
+      decorator    : Decorator::new(registry, seed),
+
+      stone: block("stone"),
+      biomes,
+    };
+  }
+
+  pub fn seed(&self) -> u64 {
+    return self.seed;
+  }
+
+  pub fn climate(&self, x: i32, z: i32) -> ColumnClimate {
+    return self.climate.sample(x, z);
+  }
+
+  /// Height of the topmost solid block of the column plus one, ignoring caves which open up at the surface.
+  pub fn height(&self, x: i32, z: i32) -> i32 {
+    return self.column_height(x, z, &self.climate(x, z));
+  }
+
   pub fn generate(&self, chunk_pos: IVec3) -> Chunk {
     let mut chunk = Chunk::default();
+    let origin = chunk_pos * CHUNK_SIZE as i32;
 
     for x in 0 .. CHUNK_SIZE {
       for z in 0 .. CHUNK_SIZE {
-        let height
-          = ((chunk_pos.x as usize * CHUNK_SIZE + x) as f32 * 0.1).sin()
-          * ((chunk_pos.x as usize * CHUNK_SIZE + z) as f32 * 0.1).cos()
-          * 10.0 + 10.0;
-
-        for y in 0 .. (height.round() as usize).max(CHUNK_SIZE) {
-          let absolute_y = chunk_pos.y as usize * CHUNK_SIZE + y;
-          if absolute_y < 32 {
-            chunk.set_block(x, y, z, BlockId::TEST);
+        let (world_x, world_z) = (origin.x + x as i32, origin.z + z as i32);
+        let climate = self.climate(world_x, world_z);
+        let height = self.column_height(world_x, world_z, &climate);
+        let blocks = &self.biomes[climate.biome.index()];
+
+        chunk.set_biome(x, z, climate.biome);
+
+        // only the part of the column below the surface intersecting this chunk
+        let top = (height - origin.y).clamp(0, CHUNK_SIZE as i32);
+        for y in 0 .. top {
+          let depth = height - (origin.y + y);
+          if self.caves.is_cheese_cave(IVec3::new(world_x, origin.y + y, world_z), depth) {
+            continue;
           }
 
-        }
+          let block = match depth {
+            1 => blocks.surface,
+            d if d <= 1 + FILLER_DEPTH => blocks.filler,
+            _ => self.stone,
+          };
 
+          chunk.set_block(x, y as usize, z, block);
+        }
       }
     }
 
+    self.caves.carve_worms(origin, origin + CHUNK_SIZE as i32, |position| {
+      let local = position - origin;
+      chunk.set_block(local.x as usize, local.y as usize, local.z as usize, BlockId::AIR);
+    });
+
+    self.decorator.decorate(self, chunk_pos, &mut chunk);
+
+    chunk.blocks.optimize();
+    chunk.biomes.optimize();
+
     return chunk;
   }
+
+  fn column_height(&self, x: i32, z: i32, climate: &ColumnClimate) -> i32 {
+    let noise = self.height_noise.sample_2d(x as f64, z as f64);
+    return (climate.base_height + noise * climate.height_amplitude).floor() as i32;
+  }
 }
\ No newline at end of file
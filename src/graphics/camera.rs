@@ -58,14 +58,20 @@ impl Transformation for Camera<TagCamera2D> {
   }
 }
 
-impl Transformation for Camera<TagCamera3D> {
-  fn apply(&self) -> Mat4 {
+impl Camera<TagCamera3D> {
+  pub fn direction(&self) -> Vec3 {
     let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
     let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
 
+    return vec3(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize();
+  }
+}
+
+impl Transformation for Camera<TagCamera3D> {
+  fn apply(&self) -> Mat4 {
     return Mat4::look_to_rh(
       self.position,
-      vec3(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize(),
+      self.direction(),
       Vec3::Y
     );
   }
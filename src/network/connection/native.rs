@@ -1,12 +1,15 @@
-use std::io::{Write, Read};
+use std::io::{ErrorKind, Read, Write};
 use anyhow::{Result, anyhow};
 use winit::event_loop::EventLoopProxy;
 use std::net::{SocketAddr, TcpStream};
 use std::sync::mpsc::{channel, Sender};
 use std::time::Duration;
 use log::error;
+use tokio_util::bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
 
 use crate::app::UVxlEvent;
+use crate::game::network::codec::{FrameCodec, MAX_FRAME_SIZE};
 use crate::game::network::packet::ServerPacket;
 
 pub struct Connection {
@@ -36,26 +39,55 @@ impl Connection {
     let socket_send = socket.try_clone()?;
     std::thread::spawn(move || {
       let mut socket = socket_send;
-      let mut buffer = vec![0u8; 1024 * 1024 * 2];
+      // the server never sends larger frames, whatever its own limit for incoming ones is
+      let mut codec = FrameCodec::new(MAX_FRAME_SIZE);
+      let mut buffer = BytesMut::new();
+      let mut chunk = vec![0u8; 64 * 1024];
+
       loop {
-        'a: {
-          let mut length = [0u8; 4];
-          let Ok(()) = socket.read_exact(&mut length) else { break 'a; };
-          let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+        // hand out every complete frame before reading more
+        match codec.decode(&mut buffer) {
+          Ok(Some(frame)) => {
+            match bincode::deserialize::<ServerPacket>(&frame) {
+              Ok(packet) => send_packet_event(packet),
+              Err(err) => error!("Failed to deserialize packet: {}", err),
+            }
 
-          let buffer = &mut buffer[.. length];
-          let Ok(()) = socket.read_exact(buffer) else { break 'a; };
+            continue;
+          }
 
-          let Ok(packet) = bincode::deserialize::<ServerPacket>(buffer) else { break 'a; };
-          send_packet_event(packet);
+          Ok(None) => { }
+          Err(err) => {
+            error!("Closing connection: {}", err);
+            return;
+          }
+        }
+
+        match socket.read(&mut chunk) {
+          Ok(0) => return, // the connection was closed
+          Ok(read) => buffer.extend_from_slice(&chunk[.. read]),
+          Err(err) if err.kind() == ErrorKind::Interrupted => { }
+          Err(err) => {
+            error!("Failed to receive data: {}", err);
+            return;
+          }
         }
       }
     });
 
     std::thread::spawn(move || {
+      let mut codec = FrameCodec::new(MAX_FRAME_SIZE);
+      let mut frame = BytesMut::new();
+
       'a: while let Ok(packet) = receiver.recv() {
+        frame.clear();
+        if let Err(err) = codec.encode(packet, &mut frame) {
+          error!("Failed to send packet: {}", err);
+          continue;
+        }
+
         let mut i = 0;
-        while let Err(err) = socket.write_all(&packet) {
+        while let Err(err) = socket.write_all(&frame) {
           error!("An error has occurred while sending a packet: {}", err);
           std::thread::sleep(Duration::from_secs(2));
 
@@ -0,0 +1,211 @@
+use anyhow::{anyhow, bail, Result};
+use glam::Vec3;
+use crate::game::world::BlockId;
+use crate::game::world::block_registry::BlockRegistry;
+
+/// Kinds of values commands take, each is parsed from one or more words.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ArgKind {
+  Integer,
+  /// Three numbers, each may be relative to the position of who runs the command, e.g. `~ ~1 ~-2.5`.
+  Coordinates,
+  /// Name of a player which is online.
+  Player,
+  /// Name of a block in the registry.
+  Block,
+  /// The rest of the command.
+  Text,
+}
+
+impl ArgKind {
+  // Words taken up by a value of this kind
+  fn width(&self) -> usize {
+    return match self {
+      ArgKind::Coordinates => 3,
+      ArgKind::Text => usize::MAX,
+      _ => 1,
+    };
+  }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Param {
+  pub name     : &'static str,
+  pub kind     : ArgKind,
+  pub optional : bool, // skipped if the words don't parse as its kind
+}
+
+impl Param {
+  pub const fn required(name: &'static str, kind: ArgKind) -> Self {
+    return Self { name, kind, optional: false };
+  }
+
+  pub const fn optional(name: &'static str, kind: ArgKind) -> Self {
+    return Self { name, kind, optional: true };
+  }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Arg {
+  Integer(i64),
+  Coordinates(Vec3),
+  Player(String),
+  Block(BlockId),
+  Text(String),
+}
+
+/// What arguments are parsed against.
+pub struct ArgContext<'a> {
+  pub origin   : Option<Vec3>, // position of who runs the command, the console has none
+  pub players  : Vec<String>, // names of the players online
+  pub registry : &'a BlockRegistry,
+}
+
+/// Parsed arguments of a command, looked up by the name of their parameter.
+#[derive(Debug, Default)]
+pub struct Args {
+  values: Vec<(&'static str, Arg)>,
+}
+
+impl Args {
+  /// Assigns the words to the parameters in order, optional ones are skipped if the words don't fit them.
+  pub fn parse(params: &[Param], words: &[&str], context: &ArgContext) -> Result<Self> {
+    let mut args = Args::default();
+    let mut words = words;
+
+    for param in params {
+      // text takes whatever is left, but at least one word
+      let width = param.kind.width().min(words.len());
+      if width == 0 || width < param.kind.width() && param.kind != ArgKind::Text {
+        if param.optional {
+          continue;
+        }
+
+        bail!("Missing {}", param.name);
+      }
+
+      match parse_arg(param.kind, &words[.. width], context) {
+        Ok(arg) => {
+          args.values.push((param.name, arg));
+          words = &words[width ..];
+        }
+
+        Err(_) if param.optional => continue,
+        Err(err) => bail!("Invalid {}: {}", param.name, err),
+      }
+    }
+
+    if !words.is_empty() {
+      bail!("Too many arguments, {} is left over", words.join(" "));
+    }
+
+    return Ok(args);
+  }
+
+  pub fn get(&self, name: &str) -> Option<&Arg> {
+    return self.values.iter().find(|x| x.0 == name).map(|x| &x.1);
+  }
+
+  pub fn integer(&self, name: &str) -> Option<i64> {
+    return match self.get(name) {
+      Some(Arg::Integer(value)) => Some(*value),
+      _ => None,
+    };
+  }
+
+  pub fn coordinates(&self, name: &str) -> Option<Vec3> {
+    return match self.get(name) {
+      Some(Arg::Coordinates(value)) => Some(*value),
+      _ => None,
+    };
+  }
+
+  pub fn player(&self, name: &str) -> Option<&str> {
+    return match self.get(name) {
+      Some(Arg::Player(value)) => Some(value),
+      _ => None,
+    };
+  }
+
+  pub fn block(&self, name: &str) -> Option<BlockId> {
+    return match self.get(name) {
+      Some(Arg::Block(value)) => Some(*value),
+      _ => None,
+    };
+  }
+
+  pub fn text(&self, name: &str) -> Option<&str> {
+    return match self.get(name) {
+      Some(Arg::Text(value)) => Some(value),
+      _ => None,
+    };
+  }
+}
+
+fn parse_arg(kind: ArgKind, words: &[&str], context: &ArgContext) -> Result<Arg> {
+  return match kind {
+    ArgKind::Integer => Ok(Arg::Integer(words[0].parse().map_err(|_| anyhow!("{} isn't a whole number", words[0]))?)),
+
+    ArgKind::Coordinates => {
+      let mut coordinates = Vec3::ZERO;
+      for (axis, word) in words.iter().enumerate() {
+        coordinates[axis] = parse_coordinate(word, context.origin.map(|x| x[axis]))?;
+      }
+
+      Ok(Arg::Coordinates(coordinates))
+    }
+
+    ArgKind::Player => match context.players.iter().find(|x| x.as_str() == words[0]) {
+      Some(name) => Ok(Arg::Player(name.clone())),
+      None => Err(anyhow!("{} isn't online", words[0])),
+    },
+
+    ArgKind::Block => match context.registry.by_name(words[0]) {
+      Some(block) => Ok(Arg::Block(block)),
+      None => Err(anyhow!("there's no block called {}", words[0])),
+    },
+
+    ArgKind::Text => Ok(Arg::Text(words.join(" "))),
+  };
+}
+
+// An absolute coordinate or an offset from the origin, prefixed with `~`
+fn parse_coordinate(word: &str, origin: Option<f32>) -> Result<f32> {
+  let value = match word.strip_prefix('~') {
+    Some(offset) => {
+      let origin = origin.ok_or_else(|| anyhow!("relative coordinates need a position to start from"))?;
+      let offset = if offset.is_empty() { 0.0 } else { offset.parse::<f32>().map_err(|_| anyhow!("{} isn't a coordinate", word))? };
+      origin + offset
+    }
+
+    None => word.parse::<f32>().map_err(|_| anyhow!("{} isn't a coordinate", word))?,
+  };
+
+  if !value.is_finite() {
+    bail!("{} isn't a coordinate", word);
+  }
+
+  return Ok(value);
+}
+
+/// Kinds of the parameters the word at `index` may belong to, optional parameters may be skipped.
+pub fn kinds_at(params: &[Param], index: usize) -> Vec<ArgKind> {
+  let mut kinds = Vec::new();
+  collect_kinds(params, index, &mut kinds);
+  kinds.dedup();
+
+  return kinds;
+}
+
+fn collect_kinds(params: &[Param], index: usize, kinds: &mut Vec<ArgKind>) {
+  let Some((param, rest)) = params.split_first() else { return };
+  if index < param.kind.width() {
+    kinds.push(param.kind);
+  } else {
+    collect_kinds(rest, index - param.kind.width(), kinds);
+  }
+
+  if param.optional {
+    collect_kinds(rest, index, kinds);
+  }
+}
\ No newline at end of file
@@ -0,0 +1,228 @@
+use std::sync::atomic::Ordering;
+use anyhow::{anyhow, bail, Context, Result};
+use crate::game::world::BlockId;
+use crate::server::command::args::{ArgKind, Args, Param};
+use crate::server::command::{Command, CommandSource, Permission};
+use crate::server::server::Server;
+
+// Most blocks /fill changes at once, a single chunk's worth
+const MAX_FILL_VOLUME: i64 = 32 * 32 * 32;
+
+// Farthest players may see in chunks, the chunks sent to each player grow with its cube
+const MAX_VIEW_DISTANCE: i64 = 16;
+
+const TP_PARAMS: &[Param] = &[Param::optional("player", ArgKind::Player), Param::required("destination", ArgKind::Coordinates)];
+const SETBLOCK_PARAMS: &[Param] = &[Param::required("position", ArgKind::Coordinates), Param::required("block", ArgKind::Block)];
+const FILL_PARAMS: &[Param] = &[Param::required("from", ArgKind::Coordinates), Param::required("to", ArgKind::Coordinates), Param::required("block", ArgKind::Block)];
+const KICK_PARAMS: &[Param] = &[Param::required("player", ArgKind::Player), Param::optional("reason", ArgKind::Text)];
+const OP_PARAMS: &[Param] = &[Param::required("player", ArgKind::Player)];
+const SAY_PARAMS: &[Param] = &[Param::required("message", ArgKind::Text)];
+const VIEW_DISTANCE_PARAMS: &[Param] = &[Param::optional("horizontal", ArgKind::Integer), Param::optional("vertical", ArgKind::Integer)];
+
+pub fn commands() -> Vec<Command> {
+  return vec![
+    Command {
+      name        : "help",
+      description : "Lists the commands you can use",
+      params      : &[],
+      permission  : Permission::Player,
+      handler     : help,
+    },
+    Command {
+      name        : "list",
+      description : "Lists the players online",
+      params      : &[],
+      permission  : Permission::Player,
+      handler     : list,
+    },
+    Command {
+      name        : "seed",
+      description : "Shows the seed the world is generated from",
+      params      : &[],
+      permission  : Permission::Player,
+      handler     : seed,
+    },
+    Command {
+      name        : "tp",
+      description : "Teleports you or another player",
+      params      : TP_PARAMS,
+      permission  : Permission::Operator,
+      handler     : teleport,
+    },
+    Command {
+      name        : "setblock",
+      description : "Places a block",
+      params      : SETBLOCK_PARAMS,
+      permission  : Permission::Operator,
+      handler     : set_block,
+    },
+    Command {
+      name        : "fill",
+      description : "Fills the box between two corners with a block",
+      params      : FILL_PARAMS,
+      permission  : Permission::Operator,
+      handler     : fill,
+    },
+    Command {
+      name        : "kick",
+      description : "Disconnects a player",
+      params      : KICK_PARAMS,
+      permission  : Permission::Operator,
+      handler     : kick,
+    },
+    Command {
+      name        : "op",
+      description : "Lets a player run every command until it disconnects",
+      params      : OP_PARAMS,
+      permission  : Permission::Operator,
+      handler     : op,
+    },
+    Command {
+      name        : "deop",
+      description : "Takes away the operator permissions of a player",
+      params      : OP_PARAMS,
+      permission  : Permission::Operator,
+      handler     : deop,
+    },
+    Command {
+      name        : "say",
+      description : "Sends a message to every player",
+      params      : SAY_PARAMS,
+      permission  : Permission::Operator,
+      handler     : say,
+    },
+    Command {
+      name        : "viewdistance",
+      description : "Shows or changes how many chunks far players see",
+      params      : VIEW_DISTANCE_PARAMS,
+      permission  : Permission::Operator,
+      handler     : view_distance,
+    },
+    Command {
+      name        : "stop",
+      description : "Saves the world and stops the server",
+      params      : &[],
+      permission  : Permission::Operator,
+      handler     : stop,
+    },
+  ];
+}
+
+fn help(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  let permission = server.permission(source);
+  return Ok(server.commands().iter()
+    .filter(|x| x.permission <= permission)
+    .map(|x| format!("{} - {}", x.usage(), x.description))
+    .collect::<Vec<_>>()
+    .join("\n"));
+}
+
+fn list(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  let players = server.player_names();
+  return Ok(format!("{} players online: {}", players.len(), players.join(", ")));
+}
+
+fn seed(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  return Ok(format!("Seed: {}", server.worldgen().seed()));
+}
+
+fn teleport(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  let destination = args.coordinates("destination").context("Missing destination")?;
+  let peer_addr = match (args.player("player"), source) {
+    (Some(name), _) => server.player_addr(name).ok_or_else(|| anyhow!("{} isn't online", name))?,
+    (None, CommandSource::Player(peer_addr)) => peer_addr,
+    (None, CommandSource::Console) => bail!("The console has to name the player to teleport"),
+  };
+
+  server.teleport(peer_addr, destination)?;
+  let name = server.player_name(peer_addr).unwrap_or_default();
+
+  return Ok(format!("Teleported {} to {:.1} {:.1} {:.1}", name, destination.x, destination.y, destination.z));
+}
+
+fn set_block(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  let position = args.coordinates("position").context("Missing position")?.floor().as_ivec3();
+  let block = args.block("block").context("Missing block")?;
+  server.set_blocks(position, position, block)?;
+
+  return Ok(format!("Set the block at {} {} {} to {}", position.x, position.y, position.z, block_name(server, block)));
+}
+
+fn fill(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  let from = args.coordinates("from").context("Missing from")?.floor().as_ivec3();
+  let to = args.coordinates("to").context("Missing to")?.floor().as_ivec3();
+  let block = args.block("block").context("Missing block")?;
+
+  // counted in 64 bits, the corners may be far apart
+  let (min, max) = (from.min(to), from.max(to));
+  let volume = (0 .. 3).map(|axis| max[axis] as i64 - min[axis] as i64 + 1).product::<i64>();
+  if volume > MAX_FILL_VOLUME {
+    bail!("Can't fill {} blocks at once, the limit is {}", volume, MAX_FILL_VOLUME);
+  }
+
+  server.set_blocks(min, max, block)?;
+  return Ok(format!("Filled {} blocks with {}", volume, block_name(server, block)));
+}
+
+fn kick(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  let name = args.player("player").context("Missing player")?;
+  let peer_addr = server.player_addr(name).ok_or_else(|| anyhow!("{} isn't online", name))?;
+  server.kick(peer_addr, args.text("reason").unwrap_or("Kicked by an operator"))?;
+
+  return Ok(format!("Kicked {}", name));
+}
+
+fn op(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  let name = args.player("player").context("Missing player")?;
+  let peer_addr = server.player_addr(name).ok_or_else(|| anyhow!("{} isn't online", name))?;
+  server.set_operator(peer_addr, true);
+  server.reply(CommandSource::Player(peer_addr), "You are an operator now");
+
+  return Ok(format!("Made {} an operator", name));
+}
+
+fn deop(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  let name = args.player("player").context("Missing player")?;
+  let peer_addr = server.player_addr(name).ok_or_else(|| anyhow!("{} isn't online", name))?;
+  server.set_operator(peer_addr, false);
+  server.reply(CommandSource::Player(peer_addr), "You are no longer an operator");
+
+  return Ok(format!("{} is no longer an operator", name));
+}
+
+fn say(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  let message = args.text("message").context("Missing message")?;
+  let sender = match source {
+    CommandSource::Player(peer_addr) => server.player_name(peer_addr).unwrap_or_default(),
+    CommandSource::Console => String::from("Server"),
+  };
+
+  server.announce(&format!("[{}] {}", sender, message))?;
+  return Ok(String::new());
+}
+
+fn view_distance(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  let settings = server.settings();
+  let current_vertical = settings.vertical_render_distance.load(Ordering::Relaxed);
+  let Some(horizontal) = args.integer("horizontal") else {
+    let current_horizontal = settings.horizontal_render_distance.load(Ordering::Relaxed);
+    return Ok(format!("Players see {} chunks far horizontally and {} vertically", current_horizontal, current_vertical));
+  };
+
+  let vertical = args.integer("vertical").unwrap_or(current_vertical as i64);
+  if !(0 ..= MAX_VIEW_DISTANCE).contains(&horizontal) || !(0 ..= MAX_VIEW_DISTANCE).contains(&vertical) {
+    bail!("View distances range from 0 to {} chunks", MAX_VIEW_DISTANCE);
+  }
+
+  server.set_view_distance(horizontal as usize, vertical as usize)?;
+  return Ok(format!("Players see {} chunks far horizontally and {} vertically now", horizontal, vertical));
+}
+
+fn stop(server: &Server, source: CommandSource, args: &Args) -> Result<String> {
+  server.stop();
+  return Ok(String::from("Saving the world and stopping the server"));
+}
+
+fn block_name(server: &Server, block: BlockId) -> String {
+  return server.registry().get(block).map_or_else(|| format!("{:?}", block), |x| x.name.clone());
+}
\ No newline at end of file
@@ -0,0 +1,117 @@
+pub mod args;
+pub mod builtin;
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use anyhow::Result;
+use crate::server::command::args::{kinds_at, ArgContext, ArgKind, Args, Param};
+use crate::server::server::Server;
+
+/// Who runs a command, replies are sent back to it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CommandSource {
+  Player(SocketAddr),
+  Console,
+}
+
+/// Commands require a permission level, operators may run every command.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Permission {
+  Player,
+  Operator,
+}
+
+/// Runs a command with its parsed arguments, returns the reply for whoever ran it.
+pub type CommandHandler = fn(&Server, CommandSource, &Args) -> Result<String>;
+
+pub struct Command {
+  pub name        : &'static str,
+  pub description : &'static str,
+  pub params      : &'static [Param],
+  pub permission  : Permission,
+  pub handler     : CommandHandler,
+}
+
+impl Command {
+  /// E.g. `/tp [player] <destination>`, optional parameters are in square brackets.
+  pub fn usage(&self) -> String {
+    let mut usage = format!("/{}", self.name);
+    for param in self.params {
+      match param.optional {
+        true => usage += &format!(" [{}]", param.name),
+        false => usage += &format!(" <{}>", param.name),
+      }
+    }
+
+    return usage;
+  }
+}
+
+#[derive(Default)]
+pub struct CommandRegistry {
+  commands: BTreeMap<&'static str, Command>,
+}
+
+impl CommandRegistry {
+  pub fn builtin() -> Self {
+    let mut registry = Self::default();
+    for command in builtin::commands() {
+      registry.register(command);
+    }
+
+    return registry;
+  }
+
+  /// Replaces any command with the same name.
+  pub fn register(&mut self, command: Command) {
+    self.commands.insert(command.name, command);
+  }
+
+  pub fn get(&self, name: &str) -> Option<&Command> {
+    return self.commands.get(name);
+  }
+
+  /// Commands in alphabetical order.
+  pub fn iter(&self) -> impl Iterator<Item = &Command> {
+    return self.commands.values();
+  }
+
+  /// Suggestions for the word at the end of `input`, which starts with a slash, and the byte offset the
+  /// word starts at. Only commands with at most the given permission level are suggested.
+  pub fn complete(&self, input: &str, permission: Permission, context: &ArgContext) -> (usize, Vec<String>) {
+    let Some(line) = input.strip_prefix('/') else { return (input.len(), Vec::new()) };
+    let start = line.rfind(' ').map_or(0, |x| x + 1);
+    let prefix = &line[start ..];
+    let words = line[.. start].split_whitespace().collect::<Vec<_>>();
+
+    let mut suggestions = match words.split_first() {
+      None => self.iter()
+        .filter(|x| x.permission <= permission)
+        .map(|x| x.name.to_string())
+        .collect(),
+
+      Some((name, words)) => match self.get(name).filter(|x| x.permission <= permission) {
+        Some(command) => kinds_at(command.params, words.len()).into_iter()
+          .flat_map(|kind| suggest(kind, context))
+          .collect(),
+
+        None => Vec::new(),
+      },
+    };
+
+    suggestions.retain(|x| x.starts_with(prefix));
+    suggestions.sort();
+    suggestions.dedup();
+
+    return (start + 1, suggestions);
+  }
+}
+
+fn suggest(kind: ArgKind, context: &ArgContext) -> Vec<String> {
+  return match kind {
+    ArgKind::Player => context.players.clone(),
+    ArgKind::Block => context.registry.iter().map(|(_, x)| x.name.clone()).collect(),
+    ArgKind::Coordinates if context.origin.is_some() => vec![String::from("~")],
+    _ => Vec::new(),
+  };
+}
\ No newline at end of file
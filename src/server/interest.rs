@@ -0,0 +1,33 @@
+use std::collections::HashSet;
+use glam::IVec3;
+use uuid::Uuid;
+
+/// What a player is told about another player after either of them moved.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Interest {
+  /// The other player came into view.
+  Spawn,
+  /// The other player is still in view, its new position is sent.
+  Move,
+  /// The other player left the view.
+  Despawn,
+  /// The other player is still out of view.
+  Hidden,
+}
+
+impl Interest {
+  /// Updates the players spawned for a client, `in_view` tells if the other player is in view of it now.
+  pub fn update(visible: &mut HashSet<Uuid>, uuid: Uuid, in_view: bool) -> Self {
+    return match in_view {
+      true if visible.insert(uuid) => Interest::Spawn,
+      true => Interest::Move,
+      false if visible.remove(&uuid) => Interest::Despawn,
+      false => Interest::Hidden,
+    };
+  }
+}
+
+/// Whether chunks are within the view distance of each other along every axis.
+pub fn in_view(a: IVec3, b: IVec3, view_distance: IVec3) -> bool {
+  return (a - b).abs().cmple(view_distance).all();
+}
\ No newline at end of file
@@ -1,4 +1,9 @@
 pub mod server;
 pub mod world;
 pub mod player;
+pub mod outbound;
+pub mod interest;
+pub mod movement;
+pub mod rate_limit;
+pub mod command;
 pub mod server_settings;
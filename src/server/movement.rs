@@ -0,0 +1,60 @@
+use std::fmt::{Display, Formatter};
+use std::time::Instant;
+use glam::{IVec3, Vec3};
+
+/// Why a move of a player was refused.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MoveError {
+  /// The movement contains NaN or infinite components.
+  NotFinite,
+  /// The player would end up beyond the world border.
+  OutOfBounds(Vec3),
+  /// The player moved farther than its speed allows, even with the tolerance.
+  TooFast { distance: f32, allowed: f32 },
+  /// The path runs through this solid block.
+  Blocked(IVec3),
+}
+
+impl Display for MoveError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    return match self {
+      MoveError::NotFinite => f.write_str("movement is not finite"),
+      MoveError::OutOfBounds(position) => write!(f, "{} is beyond the world border", position),
+      MoveError::TooFast { distance, allowed } => write!(f, "moved {:.2} blocks, {:.2} are allowed", distance, allowed),
+      MoveError::Blocked(position) => write!(f, "the path is blocked at {}", position),
+    };
+  }
+}
+
+/// Distance a player may still travel, refilled over time at its maximum speed.
+///
+/// Moves arrive in bursts whenever the connection stutters, so the budget builds up to the tolerance
+/// while the player stands still or moves slower than it could. It starts out full.
+#[derive(Debug)]
+pub struct MovementBudget {
+  available : f32, // in blocks
+  updated   : Instant,
+}
+
+impl MovementBudget {
+  pub fn new(now: Instant) -> Self {
+    return Self {
+      available : f32::MAX, // capped at the tolerance on the first move
+      updated   : now,
+    };
+  }
+
+  /// Refills the budget at `speed` blocks per second up to `tolerance` blocks, then takes `distance` out of it if that much is left.
+  pub fn take(&mut self, now: Instant, distance: f32, speed: f32, tolerance: f32) -> Result<(), MoveError> {
+    let elapsed = now.saturating_duration_since(self.updated).as_secs_f32();
+    self.updated = now;
+    self.available = (self.available + elapsed * speed).min(tolerance.max(0.0));
+
+    if distance > self.available {
+      return Err(MoveError::TooFast { distance, allowed: self.available });
+    }
+
+    self.available -= distance;
+    return Ok(());
+  }
+}
\ No newline at end of file
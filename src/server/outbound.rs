@@ -0,0 +1,185 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Instant;
+use glam::IVec3;
+use log::warn;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+// Queues grow up to this many times their limit before they are closed right away
+const HARD_LIMIT_FACTOR: usize = 2;
+
+/// What a packet carries, decides where it's queued and whether it supersedes queued packets.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Traffic {
+  /// Small packets which matter right away, sent before any bulk data.
+  Priority,
+  /// Position of the player with this uuid, replaces its position which hasn't been sent yet.
+  Movement(Uuid),
+  /// Spawn or despawn of the player with this uuid, drops its position which hasn't been sent yet. Positions
+  /// queued after it must not be coalesced with ones queued before, they would overtake it.
+  Visibility(Uuid),
+  /// Change of a block in this chunk, waits for the chunk's data if that is still queued.
+  BlockUpdate(IVec3),
+  /// Data of this chunk, sent once no priority packets are waiting.
+  Chunk(IVec3),
+  /// Unloading of this chunk, drops its data if that hasn't been sent yet. The unload itself is only dropped
+  /// as well if the client never received the chunk.
+  ChunkUnload(IVec3),
+}
+
+/// Packets waiting to be sent to a peer.
+///
+/// Priority packets are always sent before bulk chunk data. The queue accepts packets above its
+/// limit, a burst of chunks after joining may exceed it, but a peer which doesn't catch up is
+/// disconnected by the server. A queue which reaches twice its limit is closed right away.
+pub struct OutboundQueue {
+  state  : Mutex<QueueState>,
+  notify : Notify,
+  limit  : usize, // in bytes
+}
+
+#[derive(Default)]
+struct QueueState {
+  priority         : VecDeque<(Option<Uuid>, Vec<u8>)>,
+  bulk             : VecDeque<(Option<IVec3>, Vec<u8>)>,
+  delivered        : HashSet<IVec3>, // chunks the client holds a copy of
+  bytes            : usize,
+  over_limit_since : Option<Instant>,
+  closed           : bool,
+}
+
+impl OutboundQueue {
+  pub fn new(limit: usize) -> Self {
+    return Self {
+      state  : Mutex::new(QueueState::default()),
+      notify : Notify::new(),
+      limit,
+    };
+  }
+
+  /// Queues a packet, packets for closed queues are dropped.
+  pub fn push(&self, data: Vec<u8>, traffic: Traffic) {
+    let mut state = self.state();
+    if state.closed {
+      return;
+    }
+
+    state.bytes += data.len();
+    match traffic {
+      Traffic::Priority => state.priority.push_back((None, data)),
+
+      Traffic::Movement(uuid) => {
+        match state.priority.iter().position(|x| x.0 == Some(uuid)) {
+          Some(index) => {
+            let previous = std::mem::replace(&mut state.priority[index].1, data);
+            state.bytes -= previous.len();
+          }
+
+          None => state.priority.push_back((Some(uuid), data)),
+        }
+      }
+
+      Traffic::Visibility(uuid) => {
+        let dropped = state.priority.iter().filter(|x| x.0 == Some(uuid)).map(|x| x.1.len()).sum::<usize>();
+        state.priority.retain(|x| x.0 != Some(uuid));
+        state.bytes -= dropped;
+        state.priority.push_back((None, data));
+      }
+
+      // the client can't apply the update before it has the chunk
+      Traffic::BlockUpdate(chunk_pos) => {
+        if state.bulk.iter().any(|x| x.0 == Some(chunk_pos)) {
+          state.bulk.push_back((None, data));
+        } else {
+          state.priority.push_back((None, data));
+        }
+      }
+
+      Traffic::Chunk(chunk_pos) => state.bulk.push_back((Some(chunk_pos), data)),
+
+      Traffic::ChunkUnload(chunk_pos) => {
+        let dropped = state.bulk.iter().filter(|x| x.0 == Some(chunk_pos)).map(|x| x.1.len()).sum::<usize>();
+        let queued = state.bulk.len();
+        state.bulk.retain(|x| x.0 != Some(chunk_pos));
+        state.bytes -= dropped;
+
+        // a chunk which is resent, e.g. after a fill, is still held by the client until it's unloaded
+        let delivered = state.delivered.remove(&chunk_pos);
+        if delivered || state.bulk.len() == queued {
+          state.priority.push_back((None, data));
+        } else {
+          state.bytes -= data.len();
+        }
+      }
+    }
+
+    if state.bytes > self.limit * HARD_LIMIT_FACTOR {
+      warn!("Closing an outbound queue holding {} bytes, its limit is {} bytes", state.bytes, self.limit);
+      state.closed = true;
+      state.priority.clear();
+      state.bulk.clear();
+      state.bytes = 0;
+    }
+
+    self.update_limit(&mut state);
+    drop(state);
+    self.notify.notify_one();
+  }
+
+  /// Waits for the next packet, returns `None` once the queue is closed and empty.
+  pub async fn next(&self) -> Option<Vec<u8>> {
+    loop {
+      {
+        let mut state = self.state();
+        let data = match state.priority.pop_front() {
+          Some((_, data)) => Some(data),
+          None => state.bulk.pop_front().map(|(chunk_pos, data)| {
+            state.delivered.extend(chunk_pos);
+            data
+          }),
+        };
+
+        if let Some(data) = data {
+          state.bytes -= data.len();
+          self.update_limit(&mut state);
+          return Some(data);
+        }
+
+        if state.closed {
+          return None;
+        }
+      }
+
+      self.notify.notified().await;
+    }
+  }
+
+  /// Stops accepting packets, the ones already queued are still handed out.
+  pub fn close(&self) {
+    self.state().closed = true;
+    self.notify.notify_one();
+  }
+
+  pub fn queued_bytes(&self) -> usize {
+    return self.state().bytes;
+  }
+
+  /// When the queue exceeded its limit, if it still does.
+  pub fn over_limit_since(&self) -> Option<Instant> {
+    return self.state().over_limit_since;
+  }
+
+  fn update_limit(&self, state: &mut QueueState) {
+    if state.bytes <= self.limit {
+      state.over_limit_since = None;
+    } else if state.over_limit_since.is_none() {
+      state.over_limit_since = Some(Instant::now());
+    }
+  }
+
+  // nothing can panic while the lock is held, but don't take the server down if it does
+  fn state(&self) -> MutexGuard<'_, QueueState> {
+    return self.state.lock().unwrap_or_else(|x| x.into_inner());
+  }
+}
\ No newline at end of file
@@ -1,22 +1,51 @@
-use tokio_tungstenite::tungstenite::Message;
-use futures_channel::mpsc::{unbounded, UnboundedSender};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
 use glam::{IVec3, ivec3};
+use uuid::Uuid;
+use crate::game::entity::orientation::Orientation;
+use crate::game::network::chunk_encoding::ChunkEncoding;
+use crate::game::network::keepalive::Keepalive;
 use crate::game::player::Player;
+use crate::server::movement::MovementBudget;
+use crate::server::outbound::OutboundQueue;
+use crate::server::rate_limit::RateLimiter;
+use crate::server::server_settings::DEFAULT_MAX_QUEUED_BYTES;
 
-pub type Tx = UnboundedSender<Message>;
+pub type Tx = Arc<OutboundQueue>;
 
 pub struct ServerPlayer {
-  pub tx         : Tx,
-  pub player     : Player,
-  pub last_chunk : IVec3,
+  pub tx              : Tx,
+  pub player          : Player,
+  pub orientation     : Orientation, // as last received, forwarded to others without converting it
+  pub last_chunk      : IVec3,
+  pub loaded_chunks   : HashSet<IVec3>, // chunks sent or queued to be sent to the client
+  pub visible_players : HashSet<Uuid>, // players spawned for the client
+  pub handshake       : bool, // the client uses a compatible protocol version
+  pub chunk_encoding  : ChunkEncoding, // agreed on when joining
+  pub keepalive       : Keepalive,
+  pub movement_budget : MovementBudget, // how far the player may still move right now
+  pub last_move       : u32, // sequence number of the latest move received, teleports correct the client as of it
+  pub chat_limiter    : RateLimiter,
+  pub operator        : bool, // granted by an operator, for this connection only, as names aren't authenticated
 }
 
 impl Default for ServerPlayer {
   fn default() -> Self {
     return Self {
-      tx         : unbounded().0,
-      player     : Player::default(),
-      last_chunk : ivec3(0, 0, 0),
+      tx              : Arc::new(OutboundQueue::new(DEFAULT_MAX_QUEUED_BYTES)),
+      player          : Player::default(),
+      orientation     : Orientation::default(),
+      last_chunk      : ivec3(0, 0, 0),
+      loaded_chunks   : HashSet::new(),
+      visible_players : HashSet::new(),
+      handshake       : false,
+      chunk_encoding  : ChunkEncoding::Paletted,
+      keepalive       : Keepalive::default(),
+      movement_budget : MovementBudget::new(Instant::now()),
+      last_move       : u32::MAX, // right before the first move
+      chat_limiter    : RateLimiter::default(),
+      operator        : false,
     };
   }
 }
\ No newline at end of file
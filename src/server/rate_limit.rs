@@ -0,0 +1,24 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Allows a number of events within a sliding window of time, e.g. chat messages of a player.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+  events: VecDeque<Instant>, // the allowed ones which are still inside of the window
+}
+
+impl RateLimiter {
+  /// Records the event and returns true if fewer than `limit` events happened within `window` before it.
+  pub fn allow(&mut self, now: Instant, limit: usize, window: Duration) -> bool {
+    while self.events.front().is_some_and(|x| now.saturating_duration_since(*x) >= window) {
+      self.events.pop_front();
+    }
+
+    if self.events.len() >= limit {
+      return false;
+    }
+
+    self.events.push_back(now);
+    return true;
+  }
+}
\ No newline at end of file
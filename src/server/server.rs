@@ -1,67 +1,117 @@
+use std::collections::HashSet;
 use std::io::ErrorKind;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 
-use futures_util::{future, pin_mut, stream::TryStreamExt, StreamExt};
+use futures_util::{future, future::Either, pin_mut, stream::TryStreamExt, Stream, StreamExt};
 
 use tap::Tap;
 use tokio::net::{TcpListener, TcpStream};
 use tokio::runtime::Runtime;
+use tokio::sync::Notify;
+use tokio_tungstenite::tungstenite;
 use tokio_tungstenite::tungstenite::protocol::Message;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use dashmap::DashMap;
-use futures_channel::mpsc::unbounded;
-use glam::{IVec3, ivec3, vec3};
-use log::{error, info};
-use tokio_util::bytes::Bytes;
-use tokio_util::codec::{BytesCodec, FramedRead, FramedWrite, LengthDelimitedCodec};
+use glam::{IVec3, ivec3, vec3, Vec3};
+use log::{debug, error, info, warn};
+use tokio_util::codec::{FramedRead, FramedWrite};
 use uuid::Uuid;
+use crate::game::chat::{sanitize, ChatError};
 use crate::game::entity::Entity;
-use crate::game::network::packet::{ClientPacket, InitialChunkDataServerPacket, ClientJoinSuccessServerPacket, ServerPacket, ClientMovePacket, PlayerJoinServerPacket, PlayerMoveServerPacket, InitialPlayerData, ErrorServerPacket, ServerError};
-use crate::game::world::chunk::ChunkVec3Ext;
+use crate::game::network::chunk_encoding::{ChunkEncoding, EncodedChunk};
+use crate::game::network::codec::{FrameCodec, FrameError, MAX_FRAME_SIZE};
+use crate::game::network::packet::{BlockBreakClientPacket, BlockPlaceClientPacket, BlockUpdateServerPacket, ChunkUnloadServerPacket, ClientPacket, InitialChunkDataServerPacket, ClientJoinSuccessServerPacket, ServerPacket, ClientMovePacket, PlayerSpawnServerPacket, PlayerMoveServerPacket, PlayerDespawnServerPacket, ErrorServerPacket, ServerError, HandshakeClientPacket, PingPacket, MoveAckServerPacket, ChatServerPacket, ChatClientPacket, CompletionRequestClientPacket, CompletionsServerPacket, PROTOCOL_VERSION, BUILD_ID};
+use crate::game::player::PLAYER_REACH;
+use crate::game::world::BlockId;
+use crate::game::world::block_registry::BlockRegistry;
+use crate::game::world::chunk::{Chunk, ChunkIVec3Ext, ChunkVec3Ext, CHUNK_SIZE};
+use crate::game::world::raycast::path_blocked;
 use crate::game::world::worldgen::worldgen::WorldGen;
+use crate::server::command::{CommandRegistry, CommandSource, Permission};
+use crate::server::command::args::{ArgContext, Args};
+use crate::server::interest::{in_view, Interest};
+use crate::server::movement::MoveError;
+use crate::server::outbound::{OutboundQueue, Traffic};
 use crate::server::player::{ServerPlayer, Tx};
 use crate::server::server_settings::ServerSettings;
 use crate::server::world::chunk_manager::ServerChunkManager;
+use crate::server::world::chunk_queue::ChunkQueue;
+use crate::server::world::storage::{WorldMetadata, WorldStorage};
 use crate::server::world::world::ServerWorld;
 
+// How often chunks out of view are checked for unloading
+const UNLOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+// How long packets still queued for a closed connection may take to be sent
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub struct Server {
-  peers    : DashMap<SocketAddr, ServerPlayer>,
-  world    : ServerWorld,
-  settings : ServerSettings,
-  worldgen : WorldGen,
+  peers       : DashMap<SocketAddr, ServerPlayer>,
+  world       : ServerWorld,
+  settings    : ServerSettings,
+  worldgen    : WorldGen,
+  chunk_queue : ChunkQueue,
+  commands    : CommandRegistry,
+  shutdown    : Notify, // notified to stop the server
 }
 
 impl Server {
-  #[allow(clippy::new_without_default)]
-  pub fn new() -> Self {
-    let world = ServerWorld {
-      chunk_manager: ServerChunkManager {
-        chunks: Default::default()
+  pub fn new(settings: ServerSettings) -> Result<Self> {
+    let storage = WorldStorage::new(&settings.world_directory)?;
+    info!("Storing world in: {}", settings.world_directory.display());
+
+    // the seed is stored with the world, chunks generated from another one wouldn't fit the saved ones
+    let metadata = match storage.load_metadata()? {
+      Some(metadata) => {
+        if settings.seed.is_some_and(|x| x != metadata.seed) {
+          warn!("Ignoring the configured seed, the world was created with seed {}", metadata.seed);
+        }
+
+        metadata
       }
-    };
 
-    let settings = ServerSettings {
-      vertical_render_distance   : 3.into(),
-      horizontal_render_distance : 2.into(),
+      None => {
+        let metadata = WorldMetadata { seed: settings.seed.unwrap_or_else(|| Uuid::new_v4().as_u64_pair().0) };
+        storage.save_metadata(&metadata)?;
+        metadata
+      }
     };
 
-    let worldgen = WorldGen {
-
+    let registry = BlockRegistry::builtin();
+    let worldgen = WorldGen::new(&registry, metadata.seed);
+    let world = ServerWorld {
+      chunk_manager: ServerChunkManager::new(Some(storage)),
+      registry,
     };
 
-    return Self {
+    return Ok(Self {
       peers: DashMap::new(),
       world,
       settings,
       worldgen,
-    };
+      chunk_queue: ChunkQueue::default(),
+      commands: CommandRegistry::builtin(),
+      shutdown: Notify::new(),
+    });
   }
 
   pub fn run(&'static self, address: SocketAddr) -> Result<()> {
     let rt = Runtime::new()?;
 
+    // chunks are generated on dedicated threads, the network tasks only queue them
+    let workers = std::thread::available_parallelism().map_or(1, |x| x.get().saturating_sub(1).max(1));
+    for i in 0 .. workers {
+      std::thread::Builder::new()
+        .name(format!("chunk-worker-{}", i))
+        .spawn(move || self.run_chunk_worker())?;
+    }
+
+    info!("Generating chunks on {} threads", workers);
+
     let (ws_listener, tcp_listener) = rt.block_on(async {
       let address_ws = address.tap_mut(|x| x.set_port(x.port() + 1));
 
@@ -83,16 +133,190 @@ impl Server {
         { tokio::spawn(handle_ws_connection(self, stream, addr)); }
     });
 
+    rt.spawn(async move {
+      let mut interval = tokio::time::interval(self.settings.save_interval);
+      interval.tick().await;
+
+      loop {
+        interval.tick().await;
+        match tokio::task::spawn_blocking(|| self.save()).await {
+          Ok(Err(err)) => error!("Failed to save the world: {:?}", err),
+          Err(err) => error!("World saving task failed: {:?}", err),
+          _ => { }
+        }
+      }
+    });
+
+    rt.spawn(async move {
+      let mut interval = tokio::time::interval(UNLOAD_INTERVAL);
+      loop {
+        interval.tick().await;
+        match tokio::task::spawn_blocking(|| self.unload_chunks()).await {
+          Ok(Err(err)) => error!("Failed to unload chunks: {:?}", err),
+          Err(err) => error!("Chunk unloading task failed: {:?}", err),
+          _ => { }
+        }
+      }
+    });
+
+    rt.spawn(async move {
+      let mut interval = tokio::time::interval(self.settings.keepalive_interval);
+      loop {
+        interval.tick().await;
+        self.keepalive();
+      }
+    });
+
     rt.block_on(async move {
       let tcp_listener = tcp_listener;
-      while let Ok((stream, addr)) = tcp_listener.accept().await
-        { tokio::spawn(handle_tcp_connection(self, stream, addr)); }
+      let accept = async {
+        while let Ok((stream, addr)) = tcp_listener.accept().await
+          { tokio::spawn(handle_tcp_connection(self, stream, addr)); }
+      };
+
+      tokio::select! {
+        _ = accept => { }
+        _ = tokio::signal::ctrl_c() => info!("Shutting down"),
+        _ = self.shutdown.notified() => info!("Shutting down"),
+      }
     });
 
+    // the connections deliver what's left in their queues while the world is saved
+    for peer_addr in self.peers.iter().map(|x| *x.key()).collect::<Vec<_>>() {
+      let packet = ServerPacket::ErrorServerPacket(ErrorServerPacket { error: ServerError::Stopping });
+      if let Err(err) = self.send(peer_addr, &packet, Traffic::Priority) {
+        error!("Failed to tell {} that the server is stopping: {:?}", peer_addr, err);
+      }
+
+      self.disconnect(peer_addr);
+    }
+
+    self.save()?;
+    info!("World saved");
+
     return Ok(());
   }
 
+  /// Makes `run` save the world and return, even if it hasn't been called yet.
+  pub fn stop(&self) {
+    self.shutdown.notify_one();
+  }
+
+  pub fn save(&self) -> Result<()> {
+    return self.world.chunk_manager.save();
+  }
+
+  /// Unloads chunks which have been out of every player's view for a while.
+  pub fn unload_chunks(&self) -> Result<()> {
+    let mut in_view = HashSet::new();
+    for peer in self.peers.iter() {
+      in_view.extend(peer.loaded_chunks.iter().copied());
+    }
+
+    let unloaded = self.world.chunk_manager.unload_unused(&in_view, self.settings.chunk_unload_delay, self.settings.max_loaded_chunks)?;
+    if unloaded > 0 {
+      debug!("Unloaded {} chunks, {} remain loaded", unloaded, self.world.chunk_manager.chunks.len());
+    }
+
+    return Ok(());
+  }
+
+  /// Pings every peer and disconnects the ones which have been silent for longer than the idle timeout,
+  /// or which have had more data waiting to be sent to them than allowed for too long.
+  pub fn keepalive(&self) {
+    let now = Instant::now();
+    let mut timed_out = Vec::new();
+    for mut peer in self.peers.iter_mut() {
+      if peer.keepalive.timed_out(now, self.settings.idle_timeout) {
+        timed_out.push(*peer.key());
+        continue;
+      }
+
+      // the client doesn't keep up with the data sent to it
+      if peer.tx.over_limit_since().is_some_and(|x| now - x >= self.settings.queue_overflow_timeout) {
+        warn!("{} can't keep up, {} bytes are waiting to be sent", peer.key(), peer.tx.queued_bytes());
+        timed_out.push(*peer.key());
+        continue;
+      }
+
+      let payload = peer.keepalive.ping(now);
+      let packet = match bincode::serialize(&ServerPacket::PingServerPacket(PingPacket { payload })) {
+        Ok(packet) => packet,
+        Err(err) => {
+          error!("Failed to serialize ping: {:?}", err);
+          continue;
+        }
+      };
+
+      peer.tx.push(packet, Traffic::Priority);
+    }
+
+    for peer_addr in timed_out {
+      info!("{} timed out", peer_addr);
+      self.disconnect(peer_addr);
+    }
+  }
+
+  // Loads or generates queued chunks and sends them to the players waiting for them
+  fn run_chunk_worker(&self) {
+    while let Some(chunk_pos) = self.chunk_queue.next() {
+      // the chunk stays locked until it's queued, block updates broadcast meanwhile are either part of it or
+      // queued after it. A snapshot sent later could undo updates the client received before it
+      let sent = self.world.chunk_manager.inspect(chunk_pos, &self.worldgen, |chunk| {
+        // encoded once for every encoding used by the players waiting for it
+        let mut packets: Vec<(ChunkEncoding, Vec<u8>)> = Vec::new();
+        for peer_addr in self.chunk_queue.finish(chunk_pos) {
+          // the chunk may have left the player's view while it was queued, the client was told to unload it already
+          let Some(encoding) = self.peers.get(&peer_addr)
+            .filter(|x| x.loaded_chunks.contains(&chunk_pos))
+            .map(|x| x.chunk_encoding) else { continue };
+
+          let packet = match Self::cached_chunk_packet(&mut packets, chunk, chunk_pos, encoding) {
+            Ok(packet) => packet,
+            Err(err) => {
+              error!("Failed to encode chunk {}: {:?}", chunk_pos, err);
+              continue;
+            }
+          };
+
+          if let Some(peer) = self.peers.get(&peer_addr) {
+            peer.tx.push(packet, Traffic::Chunk(chunk_pos));
+          }
+        }
+      });
+
+      // the players waiting for it go without, the stored copy stays untouched until it's fixed
+      if let Err(err) = sent {
+        error!("{:?}", err);
+        self.chunk_queue.finish(chunk_pos);
+      }
+    }
+  }
+
+  fn chunk_packet(chunk: &Chunk, position: IVec3, encoding: ChunkEncoding) -> Result<Vec<u8>> {
+    return Ok(bincode::serialize(&ServerPacket::InitialChunkDataServerPacket(InitialChunkDataServerPacket {
+      chunk: EncodedChunk::encode(chunk, encoding)?,
+      position,
+    }))?);
+  }
+
+  // Packets already encoded are reused for players which use the same encoding
+  fn cached_chunk_packet(packets: &mut Vec<(ChunkEncoding, Vec<u8>)>, chunk: &Chunk, position: IVec3, encoding: ChunkEncoding) -> Result<Vec<u8>> {
+    if let Some((_, packet)) = packets.iter().find(|x| x.0 == encoding) {
+      return Ok(packet.clone());
+    }
+
+    let packet = Self::chunk_packet(chunk, position, encoding)?;
+    packets.push((encoding, packet.clone()));
+
+    return Ok(packet);
+  }
+
   pub fn handle_packet(&self, packet: &[u8], peer_addr: SocketAddr) -> Result<()> {
+    if let Some(mut peer) = self.peers.get_mut(&peer_addr) {
+      peer.keepalive.received(Instant::now());
+    }
+
     let packet = match bincode::deserialize::<ClientPacket>(packet) {
       Ok(packet) => packet,
       Err(err) => {
@@ -101,7 +325,33 @@ impl Server {
       }
     };
 
+    // clients which skip the handshake predate it, they are incompatible as well
+    let handshake = self.peers.get(&peer_addr).is_some_and(|x| x.handshake);
+    let compatible = match &packet {
+      ClientPacket::HandshakeClientPacket(packet) => packet.protocol_version == PROTOCOL_VERSION,
+      _ => handshake,
+    };
+
+    if !compatible {
+      info!("Rejecting incompatible client {}: {:?}", peer_addr, packet);
+      self.send(peer_addr, &ServerPacket::ErrorServerPacket(ErrorServerPacket {
+        error: ServerError::IncompatibleVersion {
+          protocol_version : PROTOCOL_VERSION,
+          build            : BUILD_ID.to_string(),
+        },
+      }), Traffic::Priority)?;
+
+      return Err(anyhow!("Incompatible client"));
+    }
+
     match packet {
+      ClientPacket::HandshakeClientPacket(HandshakeClientPacket { build, .. }) => {
+        info!("{} connected with {}", peer_addr, build);
+        if let Some(mut peer) = self.peers.get_mut(&peer_addr) {
+          peer.handshake = true;
+        }
+      }
+
       ClientPacket::ClientJoinClientPacket(packet) => {
         if self.peers.iter().filter(|peer| *peer.key() != peer_addr).any(|peer| peer.player.name == packet.name) {
           error!("Player with name {} is already connected to the server", packet.name);
@@ -110,202 +360,662 @@ impl Server {
             error: ServerError::PlayerLoggedIn,
           }))?;
 
-          self.peers.get(&peer_addr).unwrap().tx.unbounded_send(Message::Binary(packet))?;
+          self.peers.get(&peer_addr).unwrap().tx.push(packet, Traffic::Priority);
           return Err(anyhow!(""));
         }
 
         let uuid = Uuid::new_v4();
-        let players_data = self.peers.iter()
-          .filter(|x| *x.key() != peer_addr)
-          .map(|x| InitialPlayerData {
-            uuid: x.player.uuid,
-            name: x.player.name.clone(),
-            position: x.player.entity.state().position,
-          })
-          .collect::<Vec<_>>();
-
-        for mut peer in self.peers.iter_mut() {
-          // notify others about the new player
-          if *peer.key() != peer_addr {
-            let packet = bincode::serialize(&ServerPacket::PlayerJoinServerPacket(PlayerJoinServerPacket {
-              name: packet.name.clone(),
-              uuid: uuid,
-              position: vec3(0.0, 0.0, 0.0),
-            }))?;
-
-            peer.tx.unbounded_send(Message::Binary(packet))?;
+        let mut chunks = Vec::new();
+        if let Some(mut peer) = self.peers.get_mut(&peer_addr) {
+          // process player info
+          peer.chunk_encoding = ChunkEncoding::negotiate(&packet.chunk_encodings, self.settings.chunk_compression);
+          debug!("{} receives chunks encoded as {:?}", packet.name, peer.chunk_encoding);
+
+          let player = &mut peer.player;
+          player.uuid = uuid;
+          player.name = packet.name.clone();
+
+          let position = vec3(16.0, self.worldgen.height(16, 16) as f32 + 2.0, 16.0);
+          let state = player.entity.state_mut();
+          state.position = position;
+          state.title    = Some(packet.name.clone());
+
+          let packet = bincode::serialize(&ServerPacket::ClientJoinSuccessServerPacket(ClientJoinSuccessServerPacket {
+            uuid,
+            position,
+            blocks: self.world.registry.clone(),
+          }))?;
+
+          peer.tx.push(packet, Traffic::Priority);
+
+          chunks = self.update_view(&mut peer, position.to_chunk_pos())?;
+        }
+
+        // players nearby are spawned for the client and the other way around
+        self.update_interest(peer_addr)?;
+        self.announce(&format!("{} joined the game", packet.name))?;
+
+        // queue the initial chunks once the peers aren't locked anymore
+        for chunk_pos in chunks {
+          self.chunk_queue.request(chunk_pos, peer_addr);
+        }
+      }
+
+      ClientPacket::ClientMovePacket(ClientMovePacket { sequence, movement, orientation }) => {
+        let mut chunks = Vec::new();
+
+        // moves are relative to the position the player joined at
+        if let Some(mut peer) = self.peers.get_mut(&peer_addr).filter(|x| !x.player.uuid.is_nil()) {
+          peer.orientation = orientation;
+          peer.last_move = sequence;
+          let checked = self.check_move(&mut peer, movement);
+          if let Err(err) = &checked {
+            debug!("Refused move of {}: {}", peer.player.name, err);
           }
 
-          // respond to the client
-          else {
-            // process player info
-            let player = &mut peer.player;
-            player.uuid = uuid;
-            player.name = packet.name.clone();
+          let state = peer.player.entity.state_mut();
+          state.position = checked.unwrap_or(state.position);
+          let position = state.position;
 
-            let position = vec3(16.0, 34.0, 16.0);
-            let state = player.entity.state_mut();
-            state.position = position;
-            state.title    = Some(packet.name.clone());
+          // the client replays the moves it made since on top of this, only the latest one matters
+          let ack = MoveAckServerPacket { sequence, position };
+          let packet = match checked {
+            Ok(_) => ServerPacket::MoveAckServerPacket(ack),
+            Err(_) => ServerPacket::MoveCorrectionServerPacket(ack),
+          };
 
-            let packet = bincode::serialize(&ServerPacket::ClientJoinSuccessServerPacket(ClientJoinSuccessServerPacket {
-              uuid,
+          peer.tx.push(bincode::serialize(&packet)?, Traffic::Movement(peer.player.uuid));
+
+          // send new chunks and unload the ones which left the view
+          let chunk_pos = position.to_chunk_pos();
+          if chunk_pos != peer.last_chunk {
+            chunks = self.update_view(&mut peer, chunk_pos)?;
+            info!("{} moved to {:?} @ {:?}", peer.player.name, position, chunk_pos);
+          }
+        }
+
+        // only players which can see the player are told about its movement
+        self.update_interest(peer_addr)?;
+
+        for chunk_pos in chunks {
+          self.chunk_queue.request(chunk_pos, peer_addr);
+        }
+      }
+
+      ClientPacket::PingClientPacket(PingPacket { payload }) => {
+        self.send(peer_addr, &ServerPacket::PongServerPacket(PingPacket { payload }), Traffic::Priority)?;
+      }
+
+      ClientPacket::PongClientPacket(PingPacket { payload }) => {
+        if let Some(mut peer) = self.peers.get_mut(&peer_addr) {
+          if let Some(rtt) = peer.keepalive.pong(Instant::now(), payload) {
+            debug!("Ping of {}: {:?}", peer_addr, rtt);
+          }
+        }
+      }
+
+      ClientPacket::BlockBreakClientPacket(BlockBreakClientPacket { position }) => {
+        // checked before the chunk is locked, movement locks the peer first and then reads chunks
+        let reachable = self.can_reach(peer_addr, position);
+        let breakable = |block: BlockId| block != BlockId::AIR && self.world.registry.get(block).is_some_and(|x| x.hardness >= 0.0);
+
+        match self.world.chunk_manager.replace_block(position, BlockId::AIR, |block| reachable && breakable(block)) {
+          None => {}
+          Some(Ok(())) => {
+            self.broadcast(&ServerPacket::BlockUpdateServerPacket(BlockUpdateServerPacket {
               position,
-              players: players_data.clone(),
-            }))?;
-
-            peer.tx.unbounded_send(Message::Binary(packet))?;
-
-            // send initial chunks
-            let chunk_pos = position.to_chunk_pos();
-            let chunk_manager = &self.world.chunk_manager;
-            let vertical_render_distance = self.settings.vertical_render_distance.load(Ordering::Relaxed) as i32;
-            let horizontal_render_distance = self.settings.horizontal_render_distance.load(Ordering::Relaxed) as i32;
-
-            for x in -horizontal_render_distance ..= horizontal_render_distance {
-              for y in -vertical_render_distance ..= vertical_render_distance {
-                for z in -horizontal_render_distance ..= horizontal_render_distance {
-                  let chunk_pos = ivec3(
-                    chunk_pos.x + x,
-                    chunk_pos.y + y,
-                    chunk_pos.z + z,
-                  );
-
-                  let chunk = chunk_manager.chunks.get(&chunk_pos).map(|x| x.clone())
-                    .unwrap_or_else(|| {
-                      let chunk = self.worldgen.generate(chunk_pos);
-                      chunk_manager.chunks.insert(chunk_pos, chunk.clone());
-                      return chunk;
-                    });
-
-                  let packet = bincode::serialize(&ServerPacket::InitialChunkDataServerPacket(InitialChunkDataServerPacket {
-                    chunk: chunk,
-                    position: ivec3(chunk_pos.x, chunk_pos.y, chunk_pos.z),
-                  }))?;
-
-                  peer.tx.unbounded_send(Message::Binary(packet))?;
-                }
-              }
-            }
+              block: BlockId::AIR,
+            }), Traffic::BlockUpdate(position.to_chunk_pos()))?;
+          }
+
+          // the client's view of the block is stale, correct it
+          Some(Err(block)) => {
+            self.send(peer_addr, &ServerPacket::BlockUpdateServerPacket(BlockUpdateServerPacket { position, block }), Traffic::BlockUpdate(position.to_chunk_pos()))?;
           }
         }
       }
 
-      ClientPacket::ClientMovePacket(ClientMovePacket { position }) => {
-        let uuid = self.peers.get(&peer_addr).unwrap().player.uuid;
-        for mut peer in self.peers.iter_mut() {
-          // notify others about player movement2
-          if *peer.key() != peer_addr {
-            let packet = bincode::serialize(&ServerPacket::PlayerMoveServerPacket(PlayerMoveServerPacket {
-              uuid,
+      ClientPacket::BlockPlaceClientPacket(BlockPlaceClientPacket { position, block: new_block }) => {
+        let reachable = self.can_reach(peer_addr, position);
+        let placeable = new_block != BlockId::AIR && self.world.registry.get(new_block).is_some();
+
+        match self.world.chunk_manager.replace_block(position, new_block, |block| reachable && placeable && block == BlockId::AIR) {
+          None => {}
+          Some(Ok(())) => {
+            self.broadcast(&ServerPacket::BlockUpdateServerPacket(BlockUpdateServerPacket {
               position,
-            }))?;
+              block: new_block,
+            }), Traffic::BlockUpdate(position.to_chunk_pos()))?;
+          }
 
-            peer.tx.unbounded_send(Message::Binary(packet))?;
+          // the client's view of the block is stale, correct it
+          Some(Err(block)) => {
+            self.send(peer_addr, &ServerPacket::BlockUpdateServerPacket(BlockUpdateServerPacket { position, block }), Traffic::BlockUpdate(position.to_chunk_pos()))?;
           }
+        }
+      }
 
-          // respond to the client
-          else {
-            let state = peer.player.entity.state_mut();
-            state.position = position;
-
-            // send new chunks
-            let chunk_pos = position.to_chunk_pos();
-            let chunk_delta = peer.last_chunk - chunk_pos;
-
-            let vertical_render_distance = self.settings.vertical_render_distance.load(Ordering::Relaxed) as i32;
-            let horizontal_render_distance = self.settings.horizontal_render_distance.load(Ordering::Relaxed) as i32;
-
-            let chunk_manager = &self.world.chunk_manager;
-            let send_chunk = |chunk_pos: IVec3, tx: &Tx| -> Result<()> {
-              let chunk = chunk_manager.chunks.get(&chunk_pos).map(|x| x.clone())
-                .unwrap_or_else(|| {
-                  let chunk = self.worldgen.generate(chunk_pos);
-                  chunk_manager.chunks.insert(chunk_pos, chunk.clone());
-                  return chunk;
-                });
-
-              let packet = bincode::serialize(&ServerPacket::InitialChunkDataServerPacket(InitialChunkDataServerPacket {
-                chunk: chunk,
-                position: ivec3(chunk_pos.x, chunk_pos.y, chunk_pos.z),
-              }))?;
-
-              tx.unbounded_send(Message::Binary(packet))?;
-
-              return Ok(());
-            };
-
-            match chunk_delta.to_array() {
-              [dx, dy, dz] if dx != 0 || dy != 0 || dz != 0 => {
-                peer.last_chunk = chunk_pos;
-
-                let dx_capped = dx.abs().min(horizontal_render_distance * 2);
-                let dz_capped = dz.abs().min(horizontal_render_distance * 2);
-                let dy_capped = dy.abs().min(vertical_render_distance * 2);
-                let x_offset = if dx.abs() > horizontal_render_distance { -dx_capped / 2 } else { horizontal_render_distance - dx.abs() + 1 };
-                let z_offset = if dz.abs() > horizontal_render_distance { -dz_capped / 2 } else { horizontal_render_distance - dz.abs() + 1 };
-                let y_offset = if dy.abs() > vertical_render_distance { -dy_capped / 2 } else { vertical_render_distance - dy.abs() + 1 };
-
-                for x in if dx != 0 { 0 ..= dx_capped } else { -horizontal_render_distance ..= horizontal_render_distance } {
-                  for y in if dy != 0 { 0 ..= dy_capped } else { -vertical_render_distance ..= vertical_render_distance } {
-                    for z in if dz != 0 { 0 ..= dz_capped } else { -horizontal_render_distance ..= horizontal_render_distance } {
-                      let chunk_pos = ivec3(
-                        chunk_pos.x - if dx != 0 { (x + x_offset) * (dx / dx.abs()) } else { -x },
-                        chunk_pos.y - if dy != 0 { (y + y_offset) * (dy / dy.abs()) } else { -y },
-                        chunk_pos.z - if dz != 0 { (z + z_offset) * (dz / dz.abs()) } else { -z }
-                      );
-
-                      send_chunk(chunk_pos, &peer.tx)?;
-                    }
-                  }
-                }
+      ClientPacket::ChatClientPacket(ChatClientPacket { message }) => {
+        let Some(mut peer) = self.peers.get_mut(&peer_addr).filter(|x| !x.player.uuid.is_nil()) else { return Ok(()) };
+        let message = match self.check_chat(&mut peer, &message) {
+          Ok(message) => message,
+          Err(ChatError::Empty) => return Ok(()),
+          Err(err) => {
+            let packet = bincode::serialize(&ServerPacket::ChatServerPacket(ChatServerPacket { sender: None, message: err.to_string() }))?;
+            peer.tx.push(packet, Traffic::Priority);
+            return Ok(());
+          }
+        };
 
-                info!("{} moved to {:?} @ {:?}", peer.player.name, position, chunk_pos);
-              }
+        let sender = peer.player.name.clone();
+        drop(peer);
+
+        if let Some(line) = message.strip_prefix('/') {
+          info!("{} ran /{}", sender, line);
+          self.execute(CommandSource::Player(peer_addr), line);
+          return Ok(());
+        }
+
+        info!("<{}> {}", sender, message);
+        self.broadcast(&ServerPacket::ChatServerPacket(ChatServerPacket { sender: Some(sender), message }), Traffic::Priority)?;
+      }
+
+      ClientPacket::CompletionRequestClientPacket(CompletionRequestClientPacket { input }) => {
+        // nothing longer could be sent as a message anyway
+        let joined = self.peers.get(&peer_addr).is_some_and(|x| !x.player.uuid.is_nil());
+        if !joined || input.chars().count() > self.settings.max_chat_length {
+          return Ok(());
+        }
+
+        let source = CommandSource::Player(peer_addr);
+        let (start, suggestions) = self.commands.complete(&input, self.permission(source), &self.arg_context(source));
+        self.send(peer_addr, &ServerPacket::CompletionsServerPacket(CompletionsServerPacket { input, start, suggestions }), Traffic::Priority)?;
+      }
+    }
+
+    return Ok(());
+  }
+
+  /// Sends a message from the server itself to every player.
+  pub fn announce(&self, message: &str) -> Result<()> {
+    return self.broadcast(&ServerPacket::ChatServerPacket(ChatServerPacket {
+      sender  : None,
+      message : message.to_string(),
+    }), Traffic::Priority);
+  }
+
+  /// Runs a command, `line` is what follows the slash. The reply or the reason it failed is sent to whoever ran it.
+  pub fn execute(&self, source: CommandSource, line: &str) {
+    let reply = match self.run_command(source, line) {
+      Ok(reply) => reply,
+      Err(err) => err.to_string(),
+    };
+
+    if !reply.is_empty() {
+      self.reply(source, &reply);
+    }
+  }
+
+  fn run_command(&self, source: CommandSource, line: &str) -> Result<String> {
+    let words = line.split_whitespace().collect::<Vec<_>>();
+    let Some((name, words)) = words.split_first() else { bail!("Type /help to list the commands") };
+
+    // commands the source may not run look the same as unknown ones
+    let command = self.commands.get(name)
+      .filter(|x| x.permission <= self.permission(source))
+      .ok_or_else(|| anyhow!("Unknown command /{}, type /help to list the commands", name))?;
+
+    let args = Args::parse(command.params, words, &self.arg_context(source))
+      .map_err(|err| anyhow!("{}, usage: {}", err, command.usage()))?;
+
+    return (command.handler)(self, source, &args);
+  }
+
+  /// Sends a message from the server to whoever ran a command, the console prints it.
+  pub fn reply(&self, source: CommandSource, message: &str) {
+    let CommandSource::Player(peer_addr) = source else {
+      println!("{}", message);
+      return;
+    };
+
+    let packet = ServerPacket::ChatServerPacket(ChatServerPacket { sender: None, message: message.to_string() });
+    if let Err(err) = self.send(peer_addr, &packet, Traffic::Priority) {
+      error!("Failed to reply to {}: {:?}", peer_addr, err);
+    }
+  }
+
+  /// The console is an operator, players only once the console or another operator made them one.
+  pub fn permission(&self, source: CommandSource) -> Permission {
+    let CommandSource::Player(peer_addr) = source else { return Permission::Operator };
+    let operator = self.peers.get(&peer_addr).is_some_and(|x| x.operator);
+
+    return if operator { Permission::Operator } else { Permission::Player };
+  }
+
+  fn arg_context(&self, source: CommandSource) -> ArgContext<'_> {
+    let origin = match source {
+      CommandSource::Player(peer_addr) => self.peers.get(&peer_addr).map(|x| x.player.entity.state().position),
+      CommandSource::Console => None,
+    };
+
+    return ArgContext {
+      origin,
+      players  : self.player_names(),
+      registry : &self.world.registry,
+    };
+  }
+
+  pub fn commands(&self) -> &CommandRegistry {
+    return &self.commands;
+  }
+
+  pub fn settings(&self) -> &ServerSettings {
+    return &self.settings;
+  }
+
+  pub fn worldgen(&self) -> &WorldGen {
+    return &self.worldgen;
+  }
+
+  pub fn registry(&self) -> &BlockRegistry {
+    return &self.world.registry;
+  }
+
+  /// Names of the players which have joined the game, in alphabetical order.
+  pub fn player_names(&self) -> Vec<String> {
+    let mut names = self.peers.iter()
+      .filter(|x| !x.player.uuid.is_nil())
+      .map(|x| x.player.name.clone())
+      .collect::<Vec<_>>();
+
+    names.sort();
+    return names;
+  }
+
+  pub fn player_name(&self, peer_addr: SocketAddr) -> Option<String> {
+    return self.peers.get(&peer_addr).filter(|x| !x.player.uuid.is_nil()).map(|x| x.player.name.clone());
+  }
+
+  pub fn player_addr(&self, name: &str) -> Option<SocketAddr> {
+    return self.peers.iter().find(|x| !x.player.uuid.is_nil() && x.player.name == name).map(|x| *x.key());
+  }
+
+  /// Grants or revokes operator permissions until the player disconnects.
+  pub fn set_operator(&self, peer_addr: SocketAddr, operator: bool) {
+    if let Some(mut peer) = self.peers.get_mut(&peer_addr) {
+      peer.operator = operator;
+      info!("{} is {}an operator now", peer.player.name, if operator { "" } else { "no longer " });
+    }
+  }
+
+  /// Moves the player, the client is corrected as if its latest move had taken it there.
+  pub fn teleport(&self, peer_addr: SocketAddr, position: Vec3) -> Result<()> {
+    if !position.is_finite() || position.abs().max_element() > self.settings.world_border {
+      bail!("{} is beyond the world border", position);
+    }
+
+    let mut chunks = Vec::new();
+    if let Some(mut peer) = self.peers.get_mut(&peer_addr).filter(|x| !x.player.uuid.is_nil()) {
+      peer.player.entity.state_mut().position = position;
+
+      // moves the client sent since are applied on top of the new position by both sides
+      let packet = ServerPacket::MoveCorrectionServerPacket(MoveAckServerPacket { sequence: peer.last_move, position });
+      peer.tx.push(bincode::serialize(&packet)?, Traffic::Movement(peer.player.uuid));
+
+      let chunk_pos = position.to_chunk_pos();
+      if chunk_pos != peer.last_chunk {
+        chunks = self.update_view(&mut peer, chunk_pos)?;
+      }
+
+      info!("Teleported {} to {:?}", peer.player.name, position);
+    }
+
+    self.update_interest(peer_addr)?;
 
-              _ => { }
+    for chunk_pos in chunks {
+      self.chunk_queue.request(chunk_pos, peer_addr);
+    }
+
+    return Ok(());
+  }
+
+  /// Sets every block in the box between the corners, loading the chunks it covers. Players are sent a
+  /// block update for a single block, changed chunks they have loaded otherwise.
+  pub fn set_blocks(&self, min: IVec3, max: IVec3, block: BlockId) -> Result<()> {
+    let border = self.settings.world_border;
+    if min.as_vec3().abs().max_element() > border || max.as_vec3().abs().max_element() > border {
+      bail!("The blocks are beyond the world border");
+    }
+
+    let chunk_manager = &self.world.chunk_manager;
+    let (min_chunk, max_chunk) = (min.to_chunk_pos(), max.to_chunk_pos());
+    let mut changed = Vec::new();
+    for x in min_chunk.x ..= max_chunk.x {
+      for y in min_chunk.y ..= max_chunk.y {
+        for z in min_chunk.z ..= max_chunk.z {
+          let chunk_pos = ivec3(x, y, z);
+
+          // the part of the box inside of the chunk, in local coordinates
+          let chunk_min = chunk_pos * CHUNK_SIZE as i32;
+          let from = (min.max(chunk_min) - chunk_min).as_uvec3();
+          let to = (max.min(chunk_min + (CHUNK_SIZE as i32 - 1)) - chunk_min).as_uvec3();
+          chunk_manager.modify(chunk_pos, &self.worldgen, |chunk| {
+            for x in from.x ..= to.x {
+              for y in from.y ..= to.y {
+                for z in from.z ..= to.z {
+                  chunk.set_block(x as usize, y as usize, z as usize, block);
+                }
+              }
             }
+          })?;
+
+          changed.push(chunk_pos);
+        }
+      }
+    }
+
+    if min == max {
+      return self.broadcast(&ServerPacket::BlockUpdateServerPacket(BlockUpdateServerPacket {
+        position: min,
+        block,
+      }), Traffic::BlockUpdate(min.to_chunk_pos()));
+    }
+
+    // locked while it's queued, like chunks sent by the workers
+    for chunk_pos in changed {
+      chunk_manager.inspect(chunk_pos, &self.worldgen, |chunk| -> Result<()> {
+        let mut packets = Vec::new();
+        for peer in self.peers.iter().filter(|x| x.loaded_chunks.contains(&chunk_pos)) {
+          let packet = Self::cached_chunk_packet(&mut packets, chunk, chunk_pos, peer.chunk_encoding)?;
+          peer.tx.push(packet, Traffic::Chunk(chunk_pos));
+        }
+
+        return Ok(());
+      })??;
+    }
+
+    return Ok(());
+  }
+
+  /// Tells the player why it's disconnected and closes the connection once that's sent.
+  pub fn kick(&self, peer_addr: SocketAddr, reason: &str) -> Result<()> {
+    self.send(peer_addr, &ServerPacket::ErrorServerPacket(ErrorServerPacket {
+      error: ServerError::Kicked { reason: reason.to_string() },
+    }), Traffic::Priority)?;
+
+    info!("Kicked {}: {}", peer_addr, reason);
+    self.disconnect(peer_addr);
+
+    return Ok(());
+  }
+
+  /// Forgets the peer once its connection is closed, other players are told that it left the game.
+  pub fn disconnect(&self, peer_addr: SocketAddr) {
+    let Some((_, peer)) = self.peers.remove(&peer_addr) else { return };
+    peer.tx.close();
+
+    if peer.player.uuid.is_nil() {
+      info!("{} disconnected", peer_addr);
+      return;
+    }
+
+    info!("{} ({}) left the game", peer.player.name, peer_addr);
+    let packet = match bincode::serialize(&ServerPacket::PlayerDespawnServerPacket(PlayerDespawnServerPacket { uuid: peer.player.uuid })) {
+      Ok(packet) => packet,
+      Err(err) => {
+        error!("Failed to notify players that {} left: {:?}", peer.player.name, err);
+        return;
+      }
+    };
+
+    for mut other in self.peers.iter_mut() {
+      if other.visible_players.remove(&peer.player.uuid) {
+        other.tx.push(packet.clone(), Traffic::Visibility(peer.player.uuid));
+      }
+    }
+
+    if let Err(err) = self.announce(&format!("{} left the game", peer.player.name)) {
+      error!("Failed to notify players that {} left: {:?}", peer.player.name, err);
+    }
+  }
+
+  /// Spawns players which came into view of the player for it and despawns the ones which left its view,
+  /// the player is spawned and despawned for the others alike. Others which can see the player are sent its position.
+  fn update_interest(&self, peer_addr: SocketAddr) -> Result<()> {
+    let Some((uuid, name, position, orientation)) = self.peers.get(&peer_addr)
+      .filter(|x| !x.player.uuid.is_nil())
+      .map(|x| (x.player.uuid, x.player.name.clone(), x.player.entity.state().position, x.orientation)) else { return Ok(()) };
+
+    let view_distance = self.view_distance();
+    let chunk_pos = position.to_chunk_pos();
+    let spawn = bincode::serialize(&ServerPacket::PlayerSpawnServerPacket(PlayerSpawnServerPacket { uuid, name, position, orientation }))?;
+    let despawn = bincode::serialize(&ServerPacket::PlayerDespawnServerPacket(PlayerDespawnServerPacket { uuid }))?;
+    let movement = bincode::serialize(&ServerPacket::PlayerMoveServerPacket(PlayerMoveServerPacket { uuid, position, orientation }))?;
+
+    // the player's own view is updated once the others aren't locked anymore
+    let mut nearby = Vec::new();
+    let mut distant = Vec::new();
+    for mut other in self.peers.iter_mut().filter(|x| *x.key() != peer_addr && !x.player.uuid.is_nil()) {
+      let other_position = other.player.entity.state().position;
+      let in_view = in_view(other_position.to_chunk_pos(), chunk_pos, view_distance);
+      match Interest::update(&mut other.visible_players, uuid, in_view) {
+        Interest::Spawn => other.tx.push(spawn.clone(), Traffic::Visibility(uuid)),
+        Interest::Move => other.tx.push(movement.clone(), Traffic::Movement(uuid)),
+        Interest::Despawn => other.tx.push(despawn.clone(), Traffic::Visibility(uuid)),
+        Interest::Hidden => { }
+      }
+
+      if !in_view {
+        distant.push(other.player.uuid);
+        continue;
+      }
+
+      nearby.push(PlayerSpawnServerPacket {
+        uuid        : other.player.uuid,
+        name        : other.player.name.clone(),
+        position    : other_position,
+        orientation : other.orientation,
+      });
+    }
+
+    let Some(mut peer) = self.peers.get_mut(&peer_addr) else { return Ok(()) };
+    // others' positions are sent when they move themselves
+    for packet in nearby {
+      let uuid = packet.uuid;
+      if Interest::update(&mut peer.visible_players, uuid, true) == Interest::Spawn {
+        peer.tx.push(bincode::serialize(&ServerPacket::PlayerSpawnServerPacket(packet))?, Traffic::Visibility(uuid));
+      }
+    }
+
+    for uuid in distant {
+      if Interest::update(&mut peer.visible_players, uuid, false) == Interest::Despawn {
+        peer.tx.push(bincode::serialize(&ServerPacket::PlayerDespawnServerPacket(PlayerDespawnServerPacket { uuid }))?, Traffic::Visibility(uuid));
+      }
+    }
+
+    return Ok(());
+  }
+
+  fn send(&self, peer_addr: SocketAddr, packet: &ServerPacket, traffic: Traffic) -> Result<()> {
+    let packet = bincode::serialize(packet)?;
+    if let Some(peer) = self.peers.get(&peer_addr) {
+      peer.tx.push(packet, traffic);
+    }
+
+    return Ok(());
+  }
+
+  // Sends the packet to every player which has joined the game
+  fn broadcast(&self, packet: &ServerPacket, traffic: Traffic) -> Result<()> {
+    let packet = bincode::serialize(packet)?;
+    for peer in self.peers.iter().filter(|x| !x.player.uuid.is_nil()) {
+      peer.tx.push(packet.clone(), traffic);
+    }
+
+    return Ok(());
+  }
+
+  /// Moves the player's view to be centered on `center`, the client is told to unload chunks which left it.
+  ///
+  /// Returns the chunks which entered the view closest first, they need to be queued once the peer isn't locked anymore.
+  fn update_view(&self, peer: &mut ServerPlayer, center: IVec3) -> Result<Vec<IVec3>> {
+    let view_distance = self.view_distance();
+    let in_view = |chunk_pos: IVec3| in_view(chunk_pos, center, view_distance);
+    peer.last_chunk = center;
+
+    let left = peer.loaded_chunks.iter().copied().filter(|x| !in_view(*x)).collect::<Vec<_>>();
+    for position in left {
+      peer.loaded_chunks.remove(&position);
+
+      let packet = bincode::serialize(&ServerPacket::ChunkUnloadServerPacket(ChunkUnloadServerPacket { position }))?;
+      peer.tx.push(packet, Traffic::ChunkUnload(position));
+    }
+
+    let mut entered = Vec::new();
+    for x in -view_distance.x ..= view_distance.x {
+      for y in -view_distance.y ..= view_distance.y {
+        for z in -view_distance.z ..= view_distance.z {
+          let chunk_pos = center + ivec3(x, y, z);
+          if peer.loaded_chunks.insert(chunk_pos) {
+            entered.push(chunk_pos);
           }
         }
       }
     }
 
+    entered.sort_by_key(|x| (*x - center).length_squared());
+
+    return Ok(entered);
+  }
+
+  /// Changes how many chunks far players see along each axis, their views are updated right away.
+  pub fn set_view_distance(&self, horizontal: usize, vertical: usize) -> Result<()> {
+    self.settings.horizontal_render_distance.store(horizontal, Ordering::Relaxed);
+    self.settings.vertical_render_distance.store(vertical, Ordering::Relaxed);
+
+    let mut players = Vec::new();
+    let mut requests = Vec::new();
+    for mut peer in self.peers.iter_mut().filter(|x| !x.player.uuid.is_nil()) {
+      let peer_addr = *peer.key();
+      let center = peer.last_chunk;
+      requests.extend(self.update_view(&mut peer, center)?.into_iter().map(|x| (x, peer_addr)));
+      players.push(peer_addr);
+    }
+
+    // players may have come into or left each other's view
+    for peer_addr in players {
+      self.update_interest(peer_addr)?;
+    }
+
+    for (chunk_pos, peer_addr) in requests {
+      self.chunk_queue.request(chunk_pos, peer_addr);
+    }
+
     return Ok(());
   }
+
+  // Distance from the player's chunk to the edge of their view in chunks along each axis
+  fn view_distance(&self) -> IVec3 {
+    let vertical = self.settings.vertical_render_distance.load(Ordering::Relaxed) as i32;
+    let horizontal = self.settings.horizontal_render_distance.load(Ordering::Relaxed) as i32;
+
+    return ivec3(horizontal, vertical, horizontal);
+  }
+
+  /// The message as it is shown to others, empty messages are dropped quietly.
+  fn check_chat(&self, peer: &mut ServerPlayer, message: &str) -> Result<String, ChatError> {
+    let message = sanitize(message, self.settings.max_chat_length)?;
+    if !peer.chat_limiter.allow(Instant::now(), self.settings.chat_rate_limit, self.settings.chat_rate_window) {
+      return Err(ChatError::RateLimited);
+    }
+
+    return Ok(message);
+  }
+
+  /// Where the player ends up after the move, checked against the world border, its speed and solid blocks.
+  fn check_move(&self, peer: &mut ServerPlayer, movement: Vec3) -> Result<Vec3, MoveError> {
+    if !movement.is_finite() {
+      return Err(MoveError::NotFinite);
+    }
+
+    let from = peer.player.entity.state().position;
+    let to = from + movement;
+    if to.abs().max_element() > self.settings.world_border {
+      return Err(MoveError::OutOfBounds(to));
+    }
+
+    // the budget never holds more than the tolerance, don't walk long paths only to refuse the move afterwards
+    let distance = movement.length();
+    if distance > self.settings.movement_tolerance {
+      return Err(MoveError::TooFast { distance, allowed: self.settings.movement_tolerance });
+    }
+
+    if let Some(block) = path_blocked(from, to, |x| self.world.is_solid(x)) {
+      return Err(MoveError::Blocked(block));
+    }
+
+    // taken last, so refused moves don't use up the budget
+    peer.movement_budget.take(Instant::now(), distance, self.settings.max_speed, self.settings.movement_tolerance)?;
+    return Ok(to);
+  }
+
+  fn can_reach(&self, peer_addr: SocketAddr, position: IVec3) -> bool {
+    let Some(peer) = self.peers.get(&peer_addr) else { return false };
+    if peer.player.uuid.is_nil() {
+      return false;
+    }
+
+    // measured to the block's center, so allow for half of its diagonal
+    let distance = peer.player.entity.state().position.distance(position.as_vec3() + 0.5);
+    return distance <= PLAYER_REACH + 0.87;
+  }
+}
+
+// Packets queued for the peer until the queue is closed
+fn outbound_stream(queue: Tx) -> impl Stream<Item = Vec<u8>> {
+  return futures_util::stream::unfold(queue, |queue| async move {
+    return queue.next().await.map(|x| (x, queue));
+  });
 }
 
 async fn handle_tcp_connection(server: &Server, mut raw_stream: TcpStream, addr: SocketAddr) {
   info!("TCP connection established: {}", addr);
 
   // Insert the write part of this peer to the peer map.
-  let (tx, rx) = unbounded();
+  let tx = Arc::new(OutboundQueue::new(server.settings.max_queued_bytes));
   server.peers.insert(addr, ServerPlayer {
-    tx,
+    tx: tx.clone(),
     .. Default::default()
   });
 
+  // frames sent must fit within what every client accepts, whatever clients may send is up to the settings
   let (incoming, outgoing) = raw_stream.split();
-  let outgoing = FramedWrite::new(outgoing, LengthDelimitedCodec::new());
-  let incoming = FramedRead::new(incoming, BytesCodec::new());
+  let outgoing = FramedWrite::new(outgoing, FrameCodec::new(MAX_FRAME_SIZE));
+  let incoming = FramedRead::new(incoming, FrameCodec::new(server.settings.max_frame_size));
 
   let broadcast_incoming = incoming.try_for_each(|msg| {
     if server.handle_packet(&msg, addr).is_err() {
-      return future::err(std::io::Error::new(ErrorKind::ConnectionRefused, ""));
+      return future::err(FrameError::Io(std::io::Error::new(ErrorKind::ConnectionRefused, "Packet refused")));
     };
 
     return future::ok(());
   });
 
-  let receive_from_others = rx
-    .map(|x| Bytes::from(x.into_data()))
+  let receive_from_others = outbound_stream(tx)
     .map(Ok)
     .forward(outgoing);
 
   pin_mut!(broadcast_incoming, receive_from_others);
-  future::select(broadcast_incoming, receive_from_others).await;
+  let remaining = future::select(broadcast_incoming, receive_from_others).await;
+  if let Either::Left((Err(FrameError::Oversized { size, max }), _)) = &remaining {
+    error!("{} sent a packet of {} bytes, the limit is {} bytes", addr, size, max);
+  }
 
-  info!("{} disconnected", &addr);
-  server.peers.remove(&addr);
+  // disconnecting closes the peer's queue, deliver what's left of it, e.g. the reason the connection was refused
+  server.disconnect(addr);
+  if let Either::Left((_, receive_from_others)) = remaining {
+    tokio::time::timeout(FLUSH_TIMEOUT, receive_from_others).await.ok();
+  }
 }
 
 async fn handle_ws_connection(server: &Server, raw_stream: TcpStream, addr: SocketAddr) {
@@ -315,27 +1025,33 @@ async fn handle_ws_connection(server: &Server, raw_stream: TcpStream, addr: Sock
   info!("WebSocket connection established: {}", addr);
 
   // Insert the write part of this peer to the peer map.
-  let (tx, rx) = unbounded();
+  let tx = Arc::new(OutboundQueue::new(server.settings.max_queued_bytes));
   server.peers.insert(addr, ServerPlayer {
-    tx,
+    tx: tx.clone(),
     .. Default::default()
   });
 
   let (outgoing, incoming) = ws_stream.split();
 
   let broadcast_incoming = incoming.try_for_each(|msg| {
-    server.handle_packet(&msg.into_data(), addr).unwrap();
+    if server.handle_packet(&msg.into_data(), addr).is_err() {
+      return future::err(tungstenite::Error::ConnectionClosed);
+    }
 
     return future::ok(());
   });
 
-  let receive_from_others = rx
+  let receive_from_others = outbound_stream(tx)
+    .map(Message::Binary)
     .map(Ok)
     .forward(outgoing);
 
   pin_mut!(broadcast_incoming, receive_from_others);
-  future::select(broadcast_incoming, receive_from_others).await;
+  let remaining = future::select(broadcast_incoming, receive_from_others).await;
 
-  info!("{} disconnected", &addr);
-  server.peers.remove(&addr);
+  // disconnecting closes the peer's queue, deliver what's left of it, e.g. the reason the connection was refused
+  server.disconnect(addr);
+  if let Either::Left((_, receive_from_others)) = remaining {
+    tokio::time::timeout(FLUSH_TIMEOUT, receive_from_others).await.ok();
+  }
 }
\ No newline at end of file
@@ -1,8 +1,90 @@
+use std::path::{Path, PathBuf};
 use std::sync::atomic::AtomicUsize;
+use std::time::Duration;
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use crate::game::chat::DEFAULT_MAX_CHAT_LENGTH;
+use crate::game::network::codec::MAX_FRAME_SIZE;
+use crate::game::network::keepalive::{DEFAULT_IDLE_TIMEOUT, DEFAULT_KEEPALIVE_INTERVAL};
+use crate::game::player::PLAYER_SPEED;
+
+pub const DEFAULT_MAX_QUEUED_BYTES: usize = 4 * 1024 * 1024;
 
 #[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct ServerSettings {
   pub vertical_render_distance   : AtomicUsize,
   pub horizontal_render_distance : AtomicUsize,
-}
\ No newline at end of file
+
+  pub world_directory : PathBuf,
+  pub save_interval   : Duration,
+  pub seed            : Option<u64>, // used when the world is created, a random one if unset, the world keeps its own afterwards
+
+  pub chunk_unload_delay : Duration, // how long chunks outside of every player's view stay loaded
+  pub max_loaded_chunks  : usize, // chunks in view of a player are never unloaded, even above the limit
+
+  pub max_frame_size    : usize, // largest packet accepted from clients in bytes, larger ones close the connection. Packets sent are limited to `MAX_FRAME_SIZE`
+  pub chunk_compression : bool, // deflate chunks for clients which support it, trades CPU time for bandwidth
+
+  pub keepalive_interval : Duration, // how often players are pinged
+  pub idle_timeout       : Duration, // players which haven't sent anything for this long are disconnected
+
+  pub max_queued_bytes       : usize, // outgoing data buffered per player, twice as much closes the connection right away
+  pub queue_overflow_timeout : Duration, // players whose queue stays above the limit for this long are disconnected
+
+  pub max_speed          : f32, // blocks per second players may move, moves beyond it are corrected
+  pub movement_tolerance : f32, // blocks players may move in a burst, must exceed the distance flown in one client tick
+  pub world_border       : f32, // players can't move farther than this from the origin along any axis
+
+  pub max_chat_length  : usize, // in characters, longer messages are refused
+  pub chat_rate_limit  : usize, // messages a player may send within `chat_rate_window`
+  pub chat_rate_window : Duration,
+}
+
+impl Default for ServerSettings {
+  fn default() -> Self {
+    return Self {
+      vertical_render_distance   : 3.into(),
+      horizontal_render_distance : 2.into(),
+
+      world_directory : PathBuf::from("world"),
+      save_interval   : Duration::from_secs(60),
+      seed            : None,
+
+      chunk_unload_delay : Duration::from_secs(30),
+      max_loaded_chunks  : 4096,
+
+      max_frame_size    : MAX_FRAME_SIZE,
+      chunk_compression : true,
+
+      keepalive_interval : DEFAULT_KEEPALIVE_INTERVAL,
+      idle_timeout       : DEFAULT_IDLE_TIMEOUT,
+
+      max_queued_bytes       : DEFAULT_MAX_QUEUED_BYTES,
+      queue_overflow_timeout : Duration::from_secs(10),
+
+      max_speed          : PLAYER_SPEED * 1.25,
+      movement_tolerance : 5.0,
+      world_border       : 100_000.0,
+
+      max_chat_length  : DEFAULT_MAX_CHAT_LENGTH,
+      chat_rate_limit  : 5,
+      chat_rate_window : Duration::from_secs(5),
+    };
+  }
+}
+
+impl ServerSettings {
+  /// Reads settings from a JSON file, creating it with default values if it doesn't exist yet.
+  pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+    let path = path.as_ref();
+    if !path.exists() {
+      let settings = Self::default();
+      std::fs::write(path, serde_json::to_string_pretty(&settings)?)?;
+
+      return Ok(settings);
+    }
+
+    return Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?);
+  }
+}
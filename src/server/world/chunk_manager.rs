@@ -1,15 +1,221 @@
-use dashmap::DashMap;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Context, Result};
+use dashmap::{DashMap, DashSet};
+use dashmap::mapref::entry::Entry;
 use glam::IVec3;
-use crate::game::world::chunk::Chunk;
+use crate::game::world::BlockId;
+use crate::game::world::chunk::{Chunk, ChunkIVec3Ext};
+use crate::game::world::worldgen::worldgen::WorldGen;
+use crate::server::world::storage::WorldStorage;
 
 pub struct ServerChunkManager {
-  pub chunks: DashMap<IVec3, Chunk>,
+  pub chunks    : DashMap<IVec3, Chunk>,
+  pub dirty     : DashSet<IVec3>,
+  pub last_seen : DashMap<IVec3, Instant>, // when the chunk was last in view of a player
+  pub storage   : Option<WorldStorage>,
+
+  io_lock : Mutex<()>, // saving and unloading must not interleave, or modified chunks could be dropped unsaved
 }
 
 impl Default for ServerChunkManager {
   fn default() -> Self {
     return Self {
-      chunks: Default::default(),
+      chunks    : Default::default(),
+      dirty     : Default::default(),
+      last_seen : Default::default(),
+      storage   : None,
+
+      io_lock : Mutex::new(()),
+    };
+  }
+}
+
+impl ServerChunkManager {
+  pub fn new(storage: Option<WorldStorage>) -> Self {
+    return Self {
+      storage,
+      .. Default::default()
     };
   }
-}
\ No newline at end of file
+
+  /// Returns the chunk at the given position, loading it from storage or generating it if it's not resident.
+  ///
+  /// Chunks which fail to load aren't generated anew, that would overwrite the stored copy once it's saved.
+  pub fn get_or_load(&self, chunk_pos: IVec3, worldgen: &WorldGen) -> Result<Chunk> {
+    if let Some(chunk) = self.chunks.get(&chunk_pos) {
+      return Ok(chunk.clone());
+    }
+
+    // loaded without holding the lock, so other chunks of the same shard stay accessible meanwhile
+    let stored = match &self.storage {
+      Some(storage) => storage.load_chunk(chunk_pos).with_context(|| format!("Failed to load chunk {}", chunk_pos))?,
+      None => None,
+    };
+
+    let generated = stored.is_none();
+    let chunk = stored.unwrap_or_else(|| worldgen.generate(chunk_pos));
+
+    // somebody else could have loaded the chunk in the meantime, their copy may already be modified
+    return Ok(match self.chunks.entry(chunk_pos) {
+      Entry::Occupied(entry) => entry.get().clone(),
+      Entry::Vacant(entry) => {
+        if generated {
+          self.dirty.insert(chunk_pos);
+        }
+
+        entry.insert(chunk).clone()
+      }
+    });
+  }
+
+  /// Reads the chunk, loading it first if it isn't resident. It can't be changed in between.
+  pub fn inspect<T>(&self, chunk_pos: IVec3, worldgen: &WorldGen, f: impl FnOnce(&Chunk) -> T) -> Result<T> {
+    loop {
+      if let Some(chunk) = self.chunks.get(&chunk_pos) {
+        return Ok(f(&chunk));
+      }
+
+      self.get_or_load(chunk_pos, worldgen)?;
+    }
+  }
+
+  /// Changes the chunk in place, loading it first if it isn't resident. It can't be unloaded in between.
+  pub fn modify<T>(&self, chunk_pos: IVec3, worldgen: &WorldGen, f: impl FnOnce(&mut Chunk) -> T) -> Result<T> {
+    loop {
+      if let Some(mut chunk) = self.chunks.get_mut(&chunk_pos) {
+        let result = f(&mut chunk);
+        self.dirty.insert(chunk_pos);
+        return Ok(result);
+      }
+
+      self.get_or_load(chunk_pos, worldgen)?;
+    }
+  }
+
+  /// Returns `None` if the chunk containing the block isn't resident.
+  pub fn get_block(&self, position: IVec3) -> Option<BlockId> {
+    let chunk = self.chunks.get(&position.to_chunk_pos())?;
+    let [x, y, z] = position.to_local_pos().to_array().map(|x| x as usize);
+
+    return Some(chunk.get_block(x, y, z));
+  }
+
+  /// Returns false if the chunk containing the block isn't resident.
+  pub fn set_block(&self, position: IVec3, block: BlockId) -> bool {
+    let chunk_pos = position.to_chunk_pos();
+    let Some(mut chunk) = self.chunks.get_mut(&chunk_pos) else { return false };
+    let [x, y, z] = position.to_local_pos().to_array().map(|x| x as usize);
+    chunk.set_block(x, y, z, block);
+    self.dirty.insert(chunk_pos);
+
+    return true;
+  }
+
+  /// Sets the block if `accept` allows replacing the current one, which is checked under the same lock as the write.
+  ///
+  /// Returns the current block if it was kept, `None` if the chunk containing the block isn't resident.
+  pub fn replace_block(&self, position: IVec3, block: BlockId, accept: impl FnOnce(BlockId) -> bool) -> Option<Result<(), BlockId>> {
+    let chunk_pos = position.to_chunk_pos();
+    let mut chunk = self.chunks.get_mut(&chunk_pos)?;
+    let [x, y, z] = position.to_local_pos().to_array().map(|x| x as usize);
+
+    let current = chunk.get_block(x, y, z);
+    if !accept(current) {
+      return Some(Err(current));
+    }
+
+    chunk.set_block(x, y, z, block);
+    self.dirty.insert(chunk_pos);
+
+    return Some(Ok(()));
+  }
+
+  pub fn mark_dirty(&self, chunk_pos: IVec3) {
+    self.dirty.insert(chunk_pos);
+  }
+
+  /// Unloads chunks which haven't been in `in_view` for `delay`, saving them first if they were modified.
+  ///
+  /// If more than `max_chunks` chunks stay loaded, chunks out of view are unloaded before their delay passes,
+  /// the ones unseen for the longest time go first. Returns the amount of unloaded chunks.
+  pub fn unload_unused(&self, in_view: &HashSet<IVec3>, delay: Duration, max_chunks: usize) -> Result<usize> {
+    let now = Instant::now();
+    let mut candidates = Vec::new();
+
+    let resident = self.chunks.iter().map(|x| *x.key()).collect::<Vec<_>>();
+    for chunk_pos in &resident {
+      if in_view.contains(chunk_pos) {
+        self.last_seen.insert(*chunk_pos, now);
+      }
+
+      // chunks which were never seen, e.g. requested by a player who left, count from now
+      else {
+        candidates.push((*chunk_pos, *self.last_seen.entry(*chunk_pos).or_insert(now)));
+      }
+    }
+
+    candidates.sort_by_key(|x| x.1);
+
+    let mut unloaded = 0;
+    for (chunk_pos, last_seen) in candidates {
+      if now - last_seen < delay && resident.len() - unloaded <= max_chunks {
+        continue;
+      }
+
+      self.unload(chunk_pos)?;
+      unloaded += 1;
+    }
+
+    self.last_seen.retain(|x, _| self.chunks.contains_key(x));
+
+    return Ok(unloaded);
+  }
+
+  /// Removes the chunk from memory, it's written to storage first if it was modified.
+  ///
+  /// Modified chunks are kept if the world isn't persistent, they couldn't be restored otherwise.
+  pub fn unload(&self, chunk_pos: IVec3) -> Result<()> {
+    if self.storage.is_none() && self.dirty.contains(&chunk_pos) {
+      return Ok(());
+    }
+
+    let _io = self.io_lock.lock().map_err(|_| anyhow!("Chunk storage lock is poisoned"))?;
+
+    // the entry stays locked until the chunk is saved, otherwise it could be loaded from its stale stored copy
+    // in the meantime, or modified after it was saved
+    let Entry::Occupied(entry) = self.chunks.entry(chunk_pos) else { return Ok(()) };
+    if self.dirty.remove(&chunk_pos).is_some() {
+      if let Some(storage) = &self.storage {
+        if let Err(err) = storage.save_chunk(chunk_pos, entry.get()) {
+          // keep the chunk around, it will be saved again later
+          self.dirty.insert(chunk_pos);
+          return Err(err);
+        }
+      }
+    }
+
+    entry.remove();
+    return Ok(());
+  }
+
+  /// Writes all modified chunks to storage and flushes it, does nothing if the world isn't persistent.
+  pub fn save(&self) -> Result<()> {
+    let Some(storage) = &self.storage else { return Ok(()) };
+    let _io = self.io_lock.lock().map_err(|_| anyhow!("Chunk storage lock is poisoned"))?;
+
+    let dirty = self.dirty.iter().map(|x| *x).collect::<Vec<_>>();
+    for chunk_pos in dirty {
+      self.dirty.remove(&chunk_pos);
+
+      let Some(chunk) = self.chunks.get(&chunk_pos).map(|x| x.clone()) else { continue };
+      if let Err(err) = storage.save_chunk(chunk_pos, &chunk) {
+        self.dirty.insert(chunk_pos);
+        return Err(err);
+      }
+    }
+
+    return storage.flush();
+  }
+}
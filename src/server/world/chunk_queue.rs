@@ -0,0 +1,56 @@
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use glam::IVec3;
+
+/// Queue of chunks waiting to be loaded or generated by the chunk workers.
+///
+/// Every chunk is queued only once, players requesting a chunk which is already queued are
+/// added to the players waiting for it instead.
+pub struct ChunkQueue {
+  sender   : Sender<IVec3>,
+  receiver : Mutex<Receiver<IVec3>>,
+  waiting  : DashMap<IVec3, Vec<SocketAddr>>,
+}
+
+impl Default for ChunkQueue {
+  fn default() -> Self {
+    let (sender, receiver) = channel();
+    return Self {
+      sender,
+      receiver : Mutex::new(receiver),
+      waiting  : DashMap::new(),
+    };
+  }
+}
+
+impl ChunkQueue {
+  pub fn request(&self, chunk_pos: IVec3, peer_addr: SocketAddr) {
+    match self.waiting.entry(chunk_pos) {
+      Entry::Occupied(mut entry) => {
+        if !entry.get().contains(&peer_addr) {
+          entry.get_mut().push(peer_addr);
+        }
+      }
+
+      Entry::Vacant(entry) => {
+        entry.insert(vec![peer_addr]);
+
+        // the receiver lives as long as the queue, so sending can't fail
+        self.sender.send(chunk_pos).ok();
+      }
+    }
+  }
+
+  /// Blocks until a chunk is queued, workers take turns receiving.
+  pub fn next(&self) -> Option<IVec3> {
+    return self.receiver.lock().ok()?.recv().ok();
+  }
+
+  /// Removes the chunk from the queue and returns the players which are waiting for it.
+  pub fn finish(&self, chunk_pos: IVec3) -> Vec<SocketAddr> {
+    return self.waiting.remove(&chunk_pos).map(|x| x.1).unwrap_or_default();
+  }
+}
\ No newline at end of file
@@ -1,2 +1,6 @@
 pub mod world;
 pub mod chunk_manager;
+pub mod region;
+pub mod storage;
+
+pub mod chunk_queue;
\ No newline at end of file
@@ -0,0 +1,214 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use glam::IVec3;
+use crate::game::world::chunk::Chunk;
+
+/// Amount of chunks stored along each axis of a region file.
+pub const REGION_SIZE: usize = 8;
+
+/// Version of the region format, must be incremented whenever the layout or the chunk encoding changes.
+pub const REGION_VERSION: u32 = 1;
+
+// Identifies region files, followed by the version
+const MAGIC: [u8; 4] = *b"UVXR";
+
+const REGION_VOLUME : usize = REGION_SIZE * REGION_SIZE * REGION_SIZE;
+const SECTOR_SIZE   : usize = 4096;
+const ENTRY_SIZE    : usize = 8;
+const PREAMBLE_SIZE : usize = 8; // magic and version
+const HEADER_SIZE   : usize = PREAMBLE_SIZE + REGION_VOLUME * ENTRY_SIZE;
+const HEADER_SECTORS: usize = HEADER_SIZE.div_ceil(SECTOR_SIZE);
+
+/// Location of a single chunk inside of the region file, both values are measured in sectors.
+#[derive(Clone, Copy, Default, Debug)]
+struct RegionEntry {
+  sector  : u32,
+  sectors : u32,
+}
+
+impl RegionEntry {
+  fn is_empty(&self) -> bool {
+    return self.sectors == 0;
+  }
+}
+
+/// A file holding up to `REGION_SIZE`³ chunks.
+///
+/// The file starts with a magic number and the format version, then an offset table which has one
+/// entry for every chunk of the region, followed by the chunk data itself. Chunk data is aligned to sectors, each chunk occupies
+/// a contiguous run of them and is prefixed with its length in bytes.
+pub struct RegionFile {
+  file    : File,
+  entries : Vec<RegionEntry>,
+  used    : Vec<bool>,
+}
+
+impl RegionFile {
+  pub fn open(path: &Path) -> Result<Self> {
+    let mut file = OpenOptions::new()
+      .read(true)
+      .write(true)
+      .create(true)
+      .truncate(false)
+      .open(path)?;
+
+    // new files get an empty header, anything shorter than that is truncated
+    let length = file.metadata()?.len() as usize;
+    if length == 0 {
+      let mut preamble = MAGIC.to_vec();
+      preamble.extend_from_slice(&REGION_VERSION.to_be_bytes());
+      file.write_all(&preamble)?;
+      file.set_len((HEADER_SECTORS * SECTOR_SIZE) as u64)?;
+    } else if length < HEADER_SECTORS * SECTOR_SIZE {
+      return Err(anyhow!("Corrupted region file {}: the header is incomplete", path.display()));
+    }
+
+    let mut header = vec![0u8; HEADER_SIZE];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut header)?;
+
+    if header[.. 4] != MAGIC {
+      return Err(anyhow!("{} isn't a region file", path.display()));
+    }
+
+    let version = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    if version != REGION_VERSION {
+      return Err(anyhow!("Region file {} has version {}, this server reads version {}", path.display(), version, REGION_VERSION));
+    }
+
+    let entries = header[PREAMBLE_SIZE ..].chunks_exact(ENTRY_SIZE)
+      .map(|x| RegionEntry {
+        sector  : u32::from_be_bytes([x[0], x[1], x[2], x[3]]),
+        sectors : u32::from_be_bytes([x[4], x[5], x[6], x[7]]),
+      })
+      .collect::<Vec<_>>();
+
+    // files are padded to whole sectors, a partial one at the end means the file was cut off
+    let total_sectors = file.metadata()?.len() as usize / SECTOR_SIZE;
+    let mut used = vec![false; total_sectors];
+    used[.. HEADER_SECTORS].fill(true);
+
+    for entry in entries.iter().filter(|x| !x.is_empty()) {
+      let start = entry.sector as usize;
+      let end = start + entry.sectors as usize;
+      if start < HEADER_SECTORS || end > total_sectors {
+        return Err(anyhow!("Corrupted region file {}: chunk data out of bounds", path.display()));
+      }
+
+      if used[start .. end].contains(&true) {
+        return Err(anyhow!("Corrupted region file {}: chunks overlap", path.display()));
+      }
+
+      used[start .. end].fill(true);
+    }
+
+    return Ok(Self {
+      file,
+      entries,
+      used,
+    });
+  }
+
+  pub fn read_chunk(&mut self, local_pos: IVec3) -> Result<Option<Chunk>> {
+    let entry = self.entries[Self::index(local_pos)];
+    if entry.is_empty() {
+      return Ok(None);
+    }
+
+    let mut length = [0u8; 4];
+    self.file.seek(SeekFrom::Start((entry.sector as usize * SECTOR_SIZE) as u64))?;
+    self.file.read_exact(&mut length)?;
+
+    let length = u32::from_be_bytes(length) as usize;
+    if length + 4 > entry.sectors as usize * SECTOR_SIZE {
+      return Err(anyhow!("Corrupted region file: chunk {} is longer than its allocation", local_pos));
+    }
+
+    let mut data = vec![0u8; length];
+    self.file.read_exact(&mut data)?;
+
+    return Ok(Some(bincode::deserialize(&data)?));
+  }
+
+  /// Writes the chunk to free sectors, its previous copy is only released once the header points at the new one,
+  /// so a failed write leaves the previous copy intact.
+  pub fn write_chunk(&mut self, local_pos: IVec3, chunk: &Chunk) -> Result<()> {
+    let data = bincode::serialize(chunk)?;
+    let index = Self::index(local_pos);
+    let sectors = (data.len() + 4).div_ceil(SECTOR_SIZE);
+
+    let sector = self.allocate(sectors);
+    self.used[sector .. sector + sectors].fill(true);
+
+    let entry = RegionEntry { sector: sector as u32, sectors: sectors as u32 };
+    if let Err(err) = self.write_data(sector, sectors, &data).and_then(|_| self.write_entry(index, entry)) {
+      self.used[sector .. sector + sectors].fill(false);
+      return Err(err);
+    }
+
+    let old = std::mem::replace(&mut self.entries[index], entry);
+    if !old.is_empty() {
+      self.used[old.sector as usize .. (old.sector + old.sectors) as usize].fill(false);
+    }
+
+    return Ok(());
+  }
+
+  pub fn flush(&mut self) -> Result<()> {
+    self.file.sync_data()?;
+
+    return Ok(());
+  }
+
+  // the data must be on disk before the header refers to it
+  fn write_data(&mut self, sector: usize, sectors: usize, data: &[u8]) -> Result<()> {
+    self.file.seek(SeekFrom::Start((sector * SECTOR_SIZE) as u64))?;
+    self.file.write_all(&(data.len() as u32).to_be_bytes())?;
+    self.file.write_all(data)?;
+
+    // pad the file so the last sector is always complete
+    let end = ((sector + sectors) * SECTOR_SIZE) as u64;
+    if self.file.metadata()?.len() < end {
+      self.file.set_len(end)?;
+    }
+
+    self.file.sync_data()?;
+
+    return Ok(());
+  }
+
+  fn write_entry(&mut self, index: usize, entry: RegionEntry) -> Result<()> {
+    let mut header_entry = [0u8; ENTRY_SIZE];
+    header_entry[.. 4].copy_from_slice(&entry.sector.to_be_bytes());
+    header_entry[4 ..].copy_from_slice(&entry.sectors.to_be_bytes());
+    self.file.seek(SeekFrom::Start((PREAMBLE_SIZE + index * ENTRY_SIZE) as u64))?;
+    self.file.write_all(&header_entry)?;
+
+    return Ok(());
+  }
+
+  /// Finds the first run of free sectors which is long enough, growing the file if there is none.
+  fn allocate(&mut self, sectors: usize) -> usize {
+    let mut run = 0;
+    for (i, used) in self.used.iter().enumerate() {
+      if *used { run = 0; continue; }
+
+      run += 1;
+      if run == sectors {
+        return i + 1 - sectors;
+      }
+    }
+
+    let start = self.used.len() - run;
+    self.used.resize(start + sectors, false);
+
+    return start;
+  }
+
+  fn index(local_pos: IVec3) -> usize {
+    let [x, y, z] = local_pos.to_array().map(|x| x as usize);
+    return x + y * REGION_SIZE + z * REGION_SIZE * REGION_SIZE;
+  }
+}
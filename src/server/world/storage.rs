@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use anyhow::{anyhow, Context, Result};
+use glam::IVec3;
+use serde::{Deserialize, Serialize};
+use crate::game::world::chunk::Chunk;
+use crate::server::world::region::{REGION_SIZE, RegionFile};
+
+const METADATA_FILE: &str = "world.json";
+
+/// What the world was created with, fixed for its whole lifetime.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WorldMetadata {
+  pub seed: u64,
+}
+
+/// On-disk world storage, chunks are grouped into region files which are opened lazily.
+pub struct WorldStorage {
+  directory : PathBuf,
+  regions   : Mutex<HashMap<IVec3, RegionFile>>,
+}
+
+impl WorldStorage {
+  pub fn new(directory: impl Into<PathBuf>) -> Result<Self> {
+    let directory = directory.into();
+    std::fs::create_dir_all(&directory)?;
+
+    return Ok(Self {
+      directory,
+      regions: Mutex::new(HashMap::new()),
+    });
+  }
+
+  /// Returns `None` if the world was never saved.
+  pub fn load_metadata(&self) -> Result<Option<WorldMetadata>> {
+    let path = self.directory.join(METADATA_FILE);
+    if !path.exists() {
+      return Ok(None);
+    }
+
+    let metadata = std::fs::read_to_string(&path)?;
+    return Ok(Some(serde_json::from_str(&metadata).with_context(|| format!("Failed to parse {}", path.display()))?));
+  }
+
+  pub fn save_metadata(&self, metadata: &WorldMetadata) -> Result<()> {
+    // written to a temporary file first, so a crash can't leave the world without its metadata
+    let path = self.directory.join(METADATA_FILE);
+    let temporary = path.with_extension("json.tmp");
+    std::fs::write(&temporary, serde_json::to_string_pretty(metadata)?)?;
+    std::fs::rename(&temporary, &path)?;
+
+    return Ok(());
+  }
+
+  pub fn load_chunk(&self, chunk_pos: IVec3) -> Result<Option<Chunk>> {
+    return self.with_region(chunk_pos, |region, local_pos| region.read_chunk(local_pos));
+  }
+
+  pub fn save_chunk(&self, chunk_pos: IVec3, chunk: &Chunk) -> Result<()> {
+    return self.with_region(chunk_pos, |region, local_pos| region.write_chunk(local_pos, chunk));
+  }
+
+  pub fn flush(&self) -> Result<()> {
+    let mut regions = self.regions.lock().map_err(|_| anyhow!("Region map lock is poisoned"))?;
+    for region in regions.values_mut() {
+      region.flush()?;
+    }
+
+    return Ok(());
+  }
+
+  fn with_region<T>(&self, chunk_pos: IVec3, f: impl FnOnce(&mut RegionFile, IVec3) -> Result<T>) -> Result<T> {
+    let region_size = IVec3::splat(REGION_SIZE as i32);
+    let region_pos = chunk_pos.div_euclid(region_size);
+    let local_pos = chunk_pos.rem_euclid(region_size);
+
+    let mut regions = self.regions.lock().map_err(|_| anyhow!("Region map lock is poisoned"))?;
+    let region = match regions.entry(region_pos) {
+      Entry::Occupied(entry) => entry.into_mut(),
+      Entry::Vacant(entry) => {
+        let path = self.directory.join(format!("r.{}.{}.{}.region", region_pos.x, region_pos.y, region_pos.z));
+        entry.insert(RegionFile::open(&path)?)
+      }
+    };
+
+    return f(region, local_pos);
+  }
+}
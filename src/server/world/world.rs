@@ -1,13 +1,26 @@
+use glam::IVec3;
+use crate::game::world::block_registry::BlockRegistry;
 use crate::server::world::chunk_manager::ServerChunkManager;
 
 pub struct ServerWorld {
   pub chunk_manager: ServerChunkManager,
+  pub registry: BlockRegistry,
 }
 
 impl Default for ServerWorld {
   fn default() -> Self {
     return Self {
       chunk_manager: ServerChunkManager::default(),
+      registry: BlockRegistry::default(),
     };
   }
+}
+
+impl ServerWorld {
+  /// Blocks in chunks which aren't loaded aren't solid.
+  pub fn is_solid(&self, position: IVec3) -> bool {
+    return self.chunk_manager.get_block(position)
+      .and_then(|x| self.registry.get(x))
+      .is_some_and(|x| x.solid);
+  }
 }
\ No newline at end of file
@@ -1,4 +1,6 @@
-#[derive(Clone, Copy)]
+use glam::{IVec3, ivec3};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Side {
   Top    , // Y+
   Bottom , // Y-
@@ -8,4 +10,19 @@ pub enum Side {
 
   Front  , // X+
   Back   , // X-
+}
+
+impl Side {
+  pub const ALL: [Side; 6] = [Side::Top, Side::Bottom, Side::Right, Side::Left, Side::Front, Side::Back];
+
+  pub const fn normal(self) -> IVec3 {
+    return match self {
+      Side::Top    => ivec3( 0,  1,  0),
+      Side::Bottom => ivec3( 0, -1,  0),
+      Side::Right  => ivec3( 0,  0,  1),
+      Side::Left   => ivec3( 0,  0, -1),
+      Side::Front  => ivec3( 1,  0,  0),
+      Side::Back   => ivec3(-1,  0,  0),
+    };
+  }
 }
\ No newline at end of file
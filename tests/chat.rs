@@ -0,0 +1,54 @@
+use uvxl::game::chat::{sanitize, ChatError};
+
+#[test]
+fn messages_are_trimmed_and_stripped_of_control_characters() {
+  assert_eq!(sanitize("  hello\u{7}\n world\t ", 256), Ok(String::from("hello world")));
+  assert_eq!(sanitize(" \r\n\u{1b} ", 256), Err(ChatError::Empty));
+}
+
+#[test]
+fn length_is_counted_in_characters() {
+  assert_eq!(sanitize("äöü", 3), Ok(String::from("äöü")));
+  assert_eq!(sanitize("äöüß", 3), Err(ChatError::TooLong { length: 4, max: 3 }));
+}
+
+#[cfg(feature = "server")]
+#[test]
+fn rate_limits_apply_within_a_sliding_window() {
+  use std::time::{Duration, Instant};
+  use uvxl::server::rate_limit::RateLimiter;
+
+  let start = Instant::now();
+  let window = Duration::from_secs(5);
+  let mut limiter = RateLimiter::default();
+
+  for i in 0 .. 3 {
+    assert!(limiter.allow(start + Duration::from_secs(i), 3, window));
+  }
+
+  // refused events don't count towards the limit
+  assert!(!limiter.allow(start + Duration::from_secs(4), 3, window));
+  assert!(limiter.allow(start + Duration::from_secs(5), 3, window));
+  assert!(!limiter.allow(start + Duration::from_secs(5), 3, window));
+  assert!(limiter.allow(start + Duration::from_secs(6), 3, window));
+}
+
+#[cfg(feature = "client")]
+#[test]
+fn completions_extend_the_input_as_far_as_they_agree() {
+  use uvxl::game::client::chat::Completion;
+
+  let completion = |suggestions: &[&str]| Completion {
+    input       : String::from("/tp ali"),
+    start       : 4,
+    suggestions : suggestions.iter().map(|x| x.to_string()).collect(),
+  };
+
+  assert_eq!(completion(&["alice"]).apply("/tp ali"), Some(String::from("/tp alice ")));
+  assert_eq!(completion(&["alice", "alina"]).apply("/tp ali"), Some(String::from("/tp ali")));
+  assert_eq!(completion(&["alïce", "alïna"]).apply("/tp ali"), Some(String::from("/tp alï")));
+  assert_eq!(completion(&[]).apply("/tp ali"), None);
+
+  // typed on in the meantime
+  assert_eq!(completion(&["alice"]).apply("/tp alic"), None);
+}
\ No newline at end of file
@@ -0,0 +1,131 @@
+use glam::{ivec3, IVec3};
+use uvxl::game::network::chunk_encoding::{ChunkEncoding, EncodedChunk};
+use uvxl::game::world::BlockId;
+use uvxl::game::world::biome::BiomeId;
+use uvxl::game::world::block_registry::BlockRegistry;
+use uvxl::game::world::chunk::{Chunk, CHUNK_AREA, CHUNK_SIZE, CHUNK_VOLUME};
+use uvxl::game::world::worldgen::worldgen::WorldGen;
+
+fn assert_same(a: &Chunk, b: &Chunk) {
+  assert!(a.blocks.iter().eq(b.blocks.iter()), "blocks differ");
+  assert!(a.biomes.iter().eq(b.biomes.iter()), "biomes differ");
+}
+
+fn round_trip(chunk: &Chunk) {
+  for encoding in ChunkEncoding::ALL {
+    let encoded = EncodedChunk::encode(chunk, encoding).unwrap();
+    assert_eq!(encoded.encoding, encoding);
+    assert_same(chunk, &encoded.decode().unwrap());
+  }
+}
+
+fn decode_runs(palette: Vec<BlockId>, runs: &[(usize, usize)]) -> anyhow::Result<Chunk> {
+  let biomes = (vec![BiomeId::iter().next().unwrap()], &[(0, CHUNK_AREA)][..]);
+  EncodedChunk::from_runs((palette, runs), biomes).unwrap().decode()
+}
+
+// Every block differs from its neighbours, the worst case for run length encoding
+fn noisy_chunk() -> Chunk {
+  let registry = BlockRegistry::builtin();
+  let blocks = registry.iter().map(|x| x.0).collect::<Vec<_>>();
+  let biomes = BiomeId::iter().collect::<Vec<_>>();
+
+  let mut chunk = Chunk::default();
+  let mut state = 0x2545_F491_4F6C_DD1Du64;
+  let mut next = || {
+    state ^= state << 13;
+    state ^= state >> 7;
+    state ^= state << 17;
+    state as usize
+  };
+
+  for x in 0 .. CHUNK_SIZE {
+    for z in 0 .. CHUNK_SIZE {
+      chunk.set_biome(x, z, biomes[next() % biomes.len()]);
+      for y in 0 .. CHUNK_SIZE {
+        chunk.set_block(x, y, z, blocks[next() % blocks.len()]);
+      }
+    }
+  }
+
+  chunk
+}
+
+#[test]
+fn empty_chunks_round_trip() {
+  round_trip(&Chunk::default());
+}
+
+#[test]
+fn generated_chunks_round_trip() {
+  let registry = BlockRegistry::builtin();
+  let worldgen = WorldGen::new(&registry, 7);
+
+  for chunk_pos in [ivec3(0, 0, 0), ivec3(0, 1, 0), ivec3(-3, -2, 5), ivec3(12, 2, -9)] {
+    round_trip(&worldgen.generate(chunk_pos));
+  }
+}
+
+#[test]
+fn noisy_chunks_round_trip() {
+  round_trip(&noisy_chunk());
+}
+
+#[test]
+fn corrupted_chunks_are_rejected() {
+  let registry = BlockRegistry::builtin();
+  let chunk = WorldGen::new(&registry, 7).generate(ivec3(0, 0, 0));
+
+  for encoding in ChunkEncoding::ALL {
+    let mut encoded = EncodedChunk::encode(&chunk, encoding).unwrap();
+    encoded.data.truncate(encoded.data.len() / 2);
+    assert!(encoded.decode().is_err(), "truncated {:?} chunk was accepted", encoding);
+  }
+
+  // the format itself is accepted when the runs are valid
+  let stone = BlockRegistry::builtin().iter().map(|x| x.0).find(|x| *x != BlockId::AIR).unwrap();
+  let chunk = decode_runs(vec![BlockId::AIR, stone], &[(0, 10), (1, CHUNK_VOLUME - 10)]).unwrap();
+  assert_eq!(chunk.blocks.iter().filter(|x| *x == BlockId::AIR).count(), 10);
+
+  let invalid: [(&str, Vec<BlockId>, &[(usize, usize)]); 5] = [
+    ("a run longer than the chunk", vec![BlockId::AIR], &[(0, CHUNK_VOLUME + 1)]),
+    ("runs shorter than the chunk", vec![BlockId::AIR], &[(0, CHUNK_VOLUME - 1)]),
+    ("an empty run", vec![BlockId::AIR], &[(0, 0), (0, CHUNK_VOLUME)]),
+    ("an index out of the palette", vec![BlockId::AIR], &[(1, CHUNK_VOLUME)]),
+    ("an empty palette", vec![], &[]),
+  ];
+
+  for (description, palette, runs) in invalid {
+    assert!(decode_runs(palette, runs).is_err(), "chunk with {} was accepted", description);
+  }
+}
+
+#[test]
+fn negotiation_prefers_the_most_compact_supported_encoding() {
+  assert_eq!(ChunkEncoding::negotiate(&ChunkEncoding::ALL, true), ChunkEncoding::Deflate);
+  assert_eq!(ChunkEncoding::negotiate(&ChunkEncoding::ALL, false), ChunkEncoding::RunLength);
+  assert_eq!(ChunkEncoding::negotiate(&[ChunkEncoding::Paletted, ChunkEncoding::RunLength], true), ChunkEncoding::RunLength);
+  assert_eq!(ChunkEncoding::negotiate(&[], true), ChunkEncoding::Paletted);
+}
+
+#[test]
+fn compact_encodings_shrink_generated_terrain() {
+  let registry = BlockRegistry::builtin();
+  let worldgen = WorldGen::new(&registry, 1337);
+
+  // the chunks a player receives when joining at the default view distance
+  let spawn = ivec3(0, worldgen.height(16, 16) / CHUNK_SIZE as i32, 0);
+  let chunks = (-2 ..= 2).flat_map(|x| (-3 ..= 3).flat_map(move |y| (-2 ..= 2).map(move |z| spawn + IVec3::new(x, y, z))))
+    .map(|x| worldgen.generate(x))
+    .collect::<Vec<_>>();
+
+  let size = |encoding| chunks.iter().map(|x| EncodedChunk::encode(x, encoding).unwrap().data.len()).sum::<usize>();
+  let uncompressed = chunks.len() * std::mem::size_of::<BlockId>() * CHUNK_SIZE.pow(3);
+  let paletted = size(ChunkEncoding::Paletted);
+  let run_length = size(ChunkEncoding::RunLength);
+  let deflate = size(ChunkEncoding::Deflate);
+
+  assert!(paletted < uncompressed, "{} bytes paletted, {} as block ids", paletted, uncompressed);
+  assert!(run_length * 2 < paletted, "{} bytes run length encoded, {} paletted", run_length, paletted);
+  assert!(deflate < run_length, "{} bytes deflated, {} run length encoded", deflate, run_length);
+}
@@ -0,0 +1,88 @@
+use tokio_util::bytes::BytesMut;
+use tokio_util::codec::{Decoder, Encoder};
+use uvxl::game::network::codec::{FrameCodec, FrameError};
+
+fn encode(frames: &[&[u8]]) -> BytesMut {
+  let mut codec = FrameCodec::default();
+  let mut stream = BytesMut::new();
+  for frame in frames {
+    codec.encode(*frame, &mut stream).unwrap();
+  }
+
+  stream
+}
+
+fn decode_all(codec: &mut FrameCodec, buffer: &mut BytesMut) -> Vec<Vec<u8>> {
+  let mut frames = Vec::new();
+  while let Some(frame) = codec.decode(buffer).unwrap() {
+    frames.push(frame.to_vec());
+  }
+
+  frames
+}
+
+#[test]
+fn coalesced_frames_are_split() {
+  let frames: [&[u8]; 4] = [b"first", b"", b"third frame", &[0xFF; 1000]];
+  let mut stream = encode(&frames);
+
+  let decoded = decode_all(&mut FrameCodec::default(), &mut stream);
+  assert_eq!(decoded, frames.map(|x| x.to_vec()));
+  assert!(stream.is_empty());
+}
+
+#[test]
+fn fragmented_frames_are_reassembled() {
+  let frames: [&[u8]; 3] = [b"hello", &[7; 300], b"world"];
+  let stream = encode(&frames);
+
+  // feed the stream one byte at a time, as if every read returned a single byte
+  let mut codec = FrameCodec::default();
+  let mut buffer = BytesMut::new();
+  let mut decoded = Vec::new();
+  for byte in stream.iter() {
+    buffer.extend_from_slice(&[*byte]);
+    decoded.extend(decode_all(&mut codec, &mut buffer));
+  }
+
+  assert_eq!(decoded, frames.map(|x| x.to_vec()));
+}
+
+#[test]
+fn frames_split_across_uneven_reads() {
+  let frames: Vec<Vec<u8>> = (0 .. 50u8).map(|i| vec![i; i as usize * 37]).collect();
+  let stream = encode(&frames.iter().map(|x| x.as_slice()).collect::<Vec<_>>());
+
+  let mut codec = FrameCodec::default();
+  let mut buffer = BytesMut::new();
+  let mut decoded = Vec::new();
+  let mut offset = 0;
+  for read in [1, 3, 4, 5, 100, 2, 1000, 7].iter().cycle() {
+    if offset == stream.len() {
+      break;
+    }
+
+    let end = (offset + read).min(stream.len());
+    buffer.extend_from_slice(&stream[offset .. end]);
+    decoded.extend(decode_all(&mut codec, &mut buffer));
+    offset = end;
+  }
+
+  assert_eq!(decoded, frames);
+}
+
+#[test]
+fn oversized_frames_are_rejected() {
+  let mut stream = encode(&[&[0; 2048]]);
+
+  let mut codec = FrameCodec::new(1024);
+  assert!(matches!(codec.decode(&mut stream), Err(FrameError::Oversized { size: 2048, max: 1024 })));
+  assert!(matches!(codec.encode(&[0u8; 2048][..], &mut BytesMut::new()), Err(FrameError::Oversized { .. })));
+}
+
+#[test]
+fn oversized_frames_are_rejected_before_they_arrive() {
+  // only the header has been received, the payload must not be waited for
+  let mut header = BytesMut::from(&u32::MAX.to_be_bytes()[..]);
+  assert!(matches!(FrameCodec::default().decode(&mut header), Err(FrameError::Oversized { .. })));
+}
\ No newline at end of file
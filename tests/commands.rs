@@ -0,0 +1,71 @@
+#![cfg(feature = "server")]
+
+use glam::vec3;
+use uvxl::game::world::block_registry::BlockRegistry;
+use uvxl::server::command::{CommandRegistry, Permission};
+use uvxl::server::command::args::{kinds_at, Arg, ArgContext, ArgKind, Args, Param};
+
+const TP: &[Param] = &[Param::optional("player", ArgKind::Player), Param::required("destination", ArgKind::Coordinates)];
+
+fn context(registry: &BlockRegistry) -> ArgContext<'_> {
+  ArgContext {
+    origin   : Some(vec3(10.0, 20.0, 30.0)),
+    players  : vec![String::from("alice"), String::from("bob")],
+    registry,
+  }
+}
+
+#[test]
+fn coordinates_may_be_relative() {
+  let registry = BlockRegistry::builtin();
+  let args = Args::parse(TP, &["bob", "~", "~-5", "1.5"], &context(&registry)).unwrap();
+  assert_eq!(args.player("player"), Some("bob"));
+  assert_eq!(args.coordinates("destination"), Some(vec3(10.0, 15.0, 1.5)));
+
+  // the console has no position to start from
+  let console = ArgContext { origin: None, .. context(&registry) };
+  assert!(Args::parse(TP, &["~", "0", "0"], &console).is_err());
+  assert!(Args::parse(TP, &["0", "NaN", "0"], &console).is_err());
+}
+
+#[test]
+fn optional_params_are_skipped_if_the_words_dont_fit() {
+  let registry = BlockRegistry::builtin();
+  let args = Args::parse(TP, &["1", "2", "3"], &context(&registry)).unwrap();
+  assert_eq!(args.get("player"), None);
+  assert_eq!(args.get("destination"), Some(&Arg::Coordinates(vec3(1.0, 2.0, 3.0))));
+
+  let error = |words: &[&str]| Args::parse(TP, words, &context(&registry)).unwrap_err().to_string();
+  assert_eq!(error(&["carol", "1", "2", "3"]), "Invalid destination: carol isn't a coordinate");
+  assert_eq!(error(&["alice", "1", "2"]), "Missing destination");
+  assert_eq!(error(&["1", "2", "3", "4"]), "Too many arguments, 4 is left over");
+
+  // text takes the rest of the words
+  let params = [Param::required("player", ArgKind::Player), Param::optional("reason", ArgKind::Text)];
+  let args = Args::parse(&params, &["alice", "too", "many", "blocks"], &context(&registry)).unwrap();
+  assert_eq!(args.text("reason"), Some("too many blocks"));
+}
+
+#[test]
+fn words_may_belong_to_several_kinds() {
+  assert_eq!(kinds_at(TP, 0), vec![ArgKind::Player, ArgKind::Coordinates]);
+  assert_eq!(kinds_at(TP, 2), vec![ArgKind::Coordinates]);
+  assert_eq!(kinds_at(TP, 3), vec![ArgKind::Coordinates]);
+  assert_eq!(kinds_at(TP, 4), vec![]);
+}
+
+#[test]
+fn completions_respect_permissions() {
+  let registry = BlockRegistry::builtin();
+  let commands = CommandRegistry::builtin();
+  assert_eq!(commands.get("tp").unwrap().usage(), "/tp [player] <destination>");
+
+  assert_eq!(commands.complete("/s", Permission::Operator, &context(&registry)), (1, vec![String::from("say"), String::from("seed"), String::from("setblock"), String::from("stop")]));
+  assert_eq!(commands.complete("/s", Permission::Player, &context(&registry)), (1, vec![String::from("seed")]));
+  assert_eq!(commands.complete("/tp ", Permission::Operator, &context(&registry)), (4, vec![String::from("alice"), String::from("bob"), String::from("~")]));
+  assert_eq!(commands.complete("/tp a", Permission::Player, &context(&registry)), (4, vec![]));
+
+  let (start, blocks) = commands.complete("/setblock ~ ~ ~ st", Permission::Operator, &context(&registry));
+  assert_eq!(start, 16);
+  assert!(blocks.contains(&String::from("stone")));
+}
\ No newline at end of file
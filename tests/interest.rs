@@ -0,0 +1,49 @@
+#![cfg(feature = "server")]
+
+use std::collections::HashSet;
+use glam::ivec3;
+use uuid::Uuid;
+use uvxl::server::interest::{in_view, Interest};
+
+#[test]
+fn view_distance_is_measured_per_axis() {
+  let view_distance = ivec3(2, 1, 2);
+  assert!(in_view(ivec3(0, 0, 0), ivec3(2, -1, -2), view_distance));
+  assert!(!in_view(ivec3(0, 0, 0), ivec3(0, 2, 0), view_distance));
+  assert!(!in_view(ivec3(5, 0, 0), ivec3(2, 0, 0), view_distance));
+}
+
+#[test]
+fn players_entering_the_view_are_spawned_once() {
+  let uuid = Uuid::new_v4();
+  let mut visible = HashSet::new();
+  assert_eq!(Interest::update(&mut visible, uuid, false), Interest::Hidden);
+  assert_eq!(Interest::update(&mut visible, uuid, true), Interest::Spawn);
+  assert_eq!(Interest::update(&mut visible, uuid, true), Interest::Move);
+  assert!(visible.contains(&uuid));
+}
+
+#[test]
+fn players_leaving_the_view_are_despawned_once() {
+  let uuid = Uuid::new_v4();
+  let mut visible = HashSet::from([uuid]);
+  assert_eq!(Interest::update(&mut visible, uuid, false), Interest::Despawn);
+  assert_eq!(Interest::update(&mut visible, uuid, false), Interest::Hidden);
+  assert!(visible.is_empty());
+
+  // coming back spawns it again
+  assert_eq!(Interest::update(&mut visible, uuid, true), Interest::Spawn);
+}
+
+#[test]
+fn joining_players_see_everybody_in_view() {
+  // a player who just joined has nobody spawned yet, the others haven't seen it either
+  let (joined, nearby, distant) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+  let mut visible = HashSet::new();
+  assert_eq!(Interest::update(&mut visible, nearby, true), Interest::Spawn);
+  assert_eq!(Interest::update(&mut visible, distant, false), Interest::Hidden);
+  assert_eq!(visible, HashSet::from([nearby]));
+
+  let mut seen_by_nearby = HashSet::from([distant]);
+  assert_eq!(Interest::update(&mut seen_by_nearby, joined, true), Interest::Spawn);
+}
\ No newline at end of file
@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+use std::f32::consts::FRAC_PI_2;
+use glam::{vec3, Quat, Vec3};
+use uvxl::game::entity::interpolation::SnapshotBuffer;
+
+fn ms(start: Instant, millis: u64) -> Instant {
+  start + Duration::from_millis(millis)
+}
+
+fn assert_near(a: Vec3, b: Vec3) {
+  assert!(a.distance(b) < 1e-4, "{} != {}", a, b);
+}
+
+#[test]
+fn positions_between_snapshots_are_interpolated() {
+  let start = Instant::now();
+  let mut buffer = SnapshotBuffer::default();
+  assert_eq!(buffer.sample(start), None);
+
+  buffer.push(ms(start, 0), vec3(0.0, 0.0, 0.0), Quat::IDENTITY);
+  buffer.push(ms(start, 50), vec3(10.0, 0.0, 0.0), Quat::IDENTITY);
+  buffer.push(ms(start, 100), vec3(10.0, 20.0, 0.0), Quat::IDENTITY);
+
+  assert_near(buffer.sample(ms(start, 25)).unwrap().0, vec3(5.0, 0.0, 0.0));
+  assert_near(buffer.sample(ms(start, 50)).unwrap().0, vec3(10.0, 0.0, 0.0));
+  assert_near(buffer.sample(ms(start, 90)).unwrap().0, vec3(10.0, 16.0, 0.0));
+}
+
+#[test]
+fn times_before_the_first_snapshot_hold_still() {
+  let start = Instant::now();
+  let mut buffer = SnapshotBuffer::default();
+  buffer.push(ms(start, 100), vec3(1.0, 2.0, 3.0), Quat::IDENTITY);
+  buffer.push(ms(start, 150), vec3(4.0, 2.0, 3.0), Quat::IDENTITY);
+
+  assert_near(buffer.sample(start).unwrap().0, vec3(1.0, 2.0, 3.0));
+}
+
+#[test]
+fn late_snapshots_are_extrapolated_for_a_while() {
+  let start = Instant::now();
+  let mut buffer = SnapshotBuffer::default();
+  buffer.push(ms(start, 0), vec3(0.0, 0.0, 0.0), Quat::IDENTITY);
+  buffer.push(ms(start, 50), vec3(1.0, 0.0, 0.0), Quat::IDENTITY);
+
+  assert_near(buffer.sample(ms(start, 100)).unwrap().0, vec3(2.0, 0.0, 0.0));
+
+  // the entity stops instead of drifting away forever
+  assert_near(buffer.sample(ms(start, 300)).unwrap().0, vec3(6.0, 0.0, 0.0));
+  assert_near(buffer.sample(ms(start, 5000)).unwrap().0, vec3(6.0, 0.0, 0.0));
+}
+
+#[test]
+fn single_snapshots_are_not_extrapolated() {
+  let start = Instant::now();
+  let mut buffer = SnapshotBuffer::default();
+  buffer.push(start, vec3(1.0, 1.0, 1.0), Quat::IDENTITY);
+
+  assert_near(buffer.sample(ms(start, 200)).unwrap().0, vec3(1.0, 1.0, 1.0));
+}
+
+#[test]
+fn snapshots_received_at_once_replace_each_other() {
+  let start = Instant::now();
+  let mut buffer = SnapshotBuffer::default();
+  buffer.push(ms(start, 0), vec3(0.0, 0.0, 0.0), Quat::IDENTITY);
+  buffer.push(ms(start, 50), vec3(1.0, 0.0, 0.0), Quat::IDENTITY);
+  buffer.push(ms(start, 50), vec3(2.0, 0.0, 0.0), Quat::IDENTITY);
+
+  assert_near(buffer.sample(ms(start, 25)).unwrap().0, vec3(1.0, 0.0, 0.0));
+}
+
+#[test]
+fn rotations_are_interpolated_and_held_after_the_last_snapshot() {
+  let start = Instant::now();
+  let mut buffer = SnapshotBuffer::default();
+  buffer.push(ms(start, 0), Vec3::ZERO, Quat::IDENTITY);
+  buffer.push(ms(start, 100), Vec3::ZERO, Quat::from_rotation_y(FRAC_PI_2));
+
+  let (_, halfway) = buffer.sample(ms(start, 50)).unwrap();
+  assert!(halfway.angle_between(Quat::from_rotation_y(FRAC_PI_2 / 2.0)) < 1e-3);
+
+  let (_, late) = buffer.sample(ms(start, 200)).unwrap();
+  assert!(late.angle_between(Quat::from_rotation_y(FRAC_PI_2)) < 1e-3);
+}
\ No newline at end of file
@@ -0,0 +1,57 @@
+use std::time::{Duration, Instant};
+use uvxl::game::network::keepalive::Keepalive;
+
+#[test]
+fn pongs_measure_the_round_trip() {
+  let start = Instant::now();
+  let mut keepalive = Keepalive::new(start);
+  assert_eq!(keepalive.rtt(), None);
+
+  let payload = keepalive.ping(start + Duration::from_millis(100));
+  assert_eq!(keepalive.pong(start + Duration::from_millis(140), payload), Some(Duration::from_millis(40)));
+  assert_eq!(keepalive.rtt(), Some(Duration::from_millis(40)));
+
+  // later samples are smoothed rather than replacing the estimate
+  let payload = keepalive.ping(start + Duration::from_millis(200));
+  keepalive.pong(start + Duration::from_millis(320), payload);
+  assert_eq!(keepalive.rtt(), Some(Duration::from_millis(50)));
+}
+
+#[test]
+fn pongs_from_the_future_are_ignored() {
+  let start = Instant::now();
+  let mut keepalive = Keepalive::new(start);
+
+  assert_eq!(keepalive.pong(start + Duration::from_secs(1), 5_000_000), None);
+  assert_eq!(keepalive.pong(start, u64::MAX), None);
+  assert_eq!(keepalive.rtt(), None);
+}
+
+#[test]
+fn pings_are_due_after_the_interval() {
+  let start = Instant::now();
+  let interval = Duration::from_secs(5);
+  let mut keepalive = Keepalive::new(start);
+
+  assert!(!keepalive.ping_due(start + Duration::from_secs(4), interval));
+  assert!(keepalive.ping_due(start + interval, interval));
+
+  keepalive.ping(start + interval);
+  assert!(!keepalive.ping_due(start + Duration::from_secs(9), interval));
+}
+
+#[test]
+fn silent_connections_time_out() {
+  let start = Instant::now();
+  let timeout = Duration::from_secs(30);
+  let mut keepalive = Keepalive::new(start);
+
+  assert!(!keepalive.timed_out(start + Duration::from_secs(29), timeout));
+  assert!(keepalive.timed_out(start + timeout, timeout));
+
+  // anything received resets the timeout, sending doesn't
+  keepalive.received(start + Duration::from_secs(20));
+  keepalive.ping(start + Duration::from_secs(45));
+  assert!(!keepalive.timed_out(start + Duration::from_secs(49), timeout));
+  assert!(keepalive.timed_out(start + Duration::from_secs(50), timeout));
+}
\ No newline at end of file
@@ -0,0 +1,61 @@
+#![cfg(feature = "server")]
+
+use std::time::{Duration, Instant};
+use glam::{ivec3, vec3, IVec3};
+use uvxl::game::world::raycast::path_blocked;
+use uvxl::server::movement::{MoveError, MovementBudget};
+
+fn wall(position: IVec3) -> bool {
+  position.x == 3
+}
+
+#[test]
+fn paths_through_solid_blocks_are_blocked() {
+  assert_eq!(path_blocked(vec3(0.5, 0.5, 0.5), vec3(2.5, 0.5, 0.5), wall), None);
+  assert_eq!(path_blocked(vec3(0.5, 0.5, 0.5), vec3(5.5, 0.5, 0.5), wall), Some(ivec3(3, 0, 0)));
+  assert_eq!(path_blocked(vec3(2.5, 0.5, 0.5), vec3(4.5, 3.5, 0.5), wall), Some(ivec3(3, 1, 0)));
+  assert_eq!(path_blocked(vec3(2.5, 0.5, 0.5), vec3(2.5, 0.5, 0.5), wall), None);
+}
+
+#[test]
+fn paths_starting_on_the_grid_are_blocked() {
+  let floor = |position: IVec3| position.y < 0;
+  assert_eq!(path_blocked(vec3(16.0, 1.0, 16.0), vec3(16.0, -1.0, 16.0), floor), Some(ivec3(16, -1, 16)));
+  assert_eq!(path_blocked(vec3(16.0, 0.0, 16.0), vec3(20.0, 0.0, 16.0), floor), None);
+
+  // standing right on top of the floor
+  assert_eq!(path_blocked(vec3(16.0, 1.0, 16.0), vec3(16.0, 0.0, 16.0), floor), None);
+}
+
+#[test]
+fn players_can_leave_the_block_they_are_stuck_in() {
+  assert_eq!(path_blocked(vec3(3.5, 0.5, 0.5), vec3(2.5, 0.5, 0.5), wall), None);
+}
+
+#[test]
+fn the_budget_refills_at_the_maximum_speed() {
+  let start = Instant::now();
+  let mut budget = MovementBudget::new(start);
+
+  // starts out full
+  assert_eq!(budget.take(start, 5.0, 10.0, 5.0), Ok(()));
+  assert_eq!(budget.take(start, 0.5, 10.0, 5.0), Err(MoveError::TooFast { distance: 0.5, allowed: 0.0 }));
+
+  // a tenth of a second at ten blocks per second
+  assert_eq!(budget.take(start + Duration::from_millis(100), 1.0, 10.0, 5.0), Ok(()));
+  assert!(budget.take(start + Duration::from_millis(200), 1.5, 10.0, 5.0).is_err());
+
+  // refused moves don't use up the budget
+  assert_eq!(budget.take(start + Duration::from_millis(200), 1.0, 10.0, 5.0), Ok(()));
+}
+
+#[test]
+fn standing_still_only_builds_up_the_tolerance() {
+  let start = Instant::now();
+  let mut budget = MovementBudget::new(start);
+  assert_eq!(budget.take(start, 0.0, 10.0, 5.0), Ok(()));
+
+  let later = start + Duration::from_secs(60);
+  assert_eq!(budget.take(later, 6.0, 10.0, 5.0), Err(MoveError::TooFast { distance: 6.0, allowed: 5.0 }));
+  assert_eq!(budget.take(later, 5.0, 10.0, 5.0), Ok(()));
+}
\ No newline at end of file
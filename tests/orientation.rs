@@ -0,0 +1,34 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+use glam::{vec3, Vec3};
+use uvxl::game::entity::orientation::Orientation;
+
+// The direction `Camera::direction` looks in for these angles
+fn camera_direction(yaw: f32, pitch: f32) -> Vec3 {
+  vec3(pitch.cos() * yaw.cos(), pitch.sin(), pitch.cos() * yaw.sin())
+}
+
+#[test]
+fn angles_survive_quantization() {
+  for (yaw, pitch) in [(0.0, 0.0), (1.0, 0.5), (3.0, -1.2), (6.0, 1.5)] {
+    let orientation = Orientation::from_angles(yaw, pitch);
+    assert!((orientation.yaw() - yaw).abs() < 1e-3, "{} != {}", orientation.yaw(), yaw);
+    assert!((orientation.pitch() - pitch).abs() < 1e-3, "{} != {}", orientation.pitch(), pitch);
+  }
+}
+
+#[test]
+fn yaw_wraps_around_and_pitch_is_clamped() {
+  assert_eq!(Orientation::from_angles(-FRAC_PI_2, 0.0), Orientation::from_angles(3.0 * FRAC_PI_2, 0.0));
+  assert_eq!(Orientation::from_angles(TAU, 0.0), Orientation::from_angles(0.0, 0.0));
+  assert_eq!(Orientation::from_angles(0.0, PI), Orientation::from_angles(0.0, FRAC_PI_2));
+  assert_eq!(Orientation::from_angles(0.0, -PI), Orientation::from_angles(0.0, -FRAC_PI_2));
+}
+
+#[test]
+fn rotation_turns_towards_the_camera_direction() {
+  for (yaw, pitch) in [(0.0, 0.0), (FRAC_PI_2, 0.0), (2.5, 0.7), (-1.0, -0.4)] {
+    let rotated = Orientation::from_angles(yaw, pitch).rotation() * Vec3::X;
+    let expected = camera_direction(yaw, pitch);
+    assert!(rotated.distance(expected) < 1e-3, "{} != {}", rotated, expected);
+  }
+}
\ No newline at end of file
@@ -0,0 +1,139 @@
+#![cfg(feature = "server")]
+
+use glam::ivec3;
+use uuid::Uuid;
+use uvxl::server::outbound::{OutboundQueue, Traffic};
+
+async fn drain(queue: &OutboundQueue) -> Vec<Vec<u8>> {
+  queue.close();
+
+  let mut packets = Vec::new();
+  while let Some(packet) = queue.next().await {
+    packets.push(packet);
+  }
+
+  packets
+}
+
+#[tokio::test]
+async fn priority_packets_overtake_chunks() {
+  let queue = OutboundQueue::new(1024);
+  queue.push(vec![1], Traffic::Chunk(ivec3(0, 0, 0)));
+  queue.push(vec![2], Traffic::Chunk(ivec3(1, 0, 0)));
+  queue.push(vec![3], Traffic::Priority);
+
+  assert_eq!(drain(&queue).await, [vec![3], vec![1], vec![2]]);
+}
+
+#[tokio::test]
+async fn movement_is_coalesced_per_player() {
+  let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+  let queue = OutboundQueue::new(1024);
+  queue.push(vec![1], Traffic::Movement(a));
+  queue.push(vec![2], Traffic::Movement(b));
+  queue.push(vec![3, 3], Traffic::Movement(a));
+  assert_eq!(queue.queued_bytes(), 3);
+
+  assert_eq!(drain(&queue).await, [vec![3, 3], vec![2]]);
+}
+
+#[tokio::test]
+async fn block_updates_wait_for_their_chunk() {
+  let queue = OutboundQueue::new(1024);
+  queue.push(vec![1], Traffic::Chunk(ivec3(0, 0, 0)));
+  queue.push(vec![2], Traffic::BlockUpdate(ivec3(0, 0, 0)));
+  queue.push(vec![3], Traffic::BlockUpdate(ivec3(5, 0, 0)));
+
+  assert_eq!(drain(&queue).await, [vec![3], vec![1], vec![2]]);
+}
+
+#[tokio::test]
+async fn unloading_drops_unsent_chunks() {
+  let queue = OutboundQueue::new(1024);
+  queue.push(vec![1; 100], Traffic::Chunk(ivec3(0, 0, 0)));
+  queue.push(vec![2], Traffic::Chunk(ivec3(1, 0, 0)));
+  queue.push(vec![3], Traffic::ChunkUnload(ivec3(0, 0, 0)));
+  queue.push(vec![4], Traffic::ChunkUnload(ivec3(7, 0, 0)));
+  assert_eq!(queue.queued_bytes(), 2);
+
+  assert_eq!(drain(&queue).await, [vec![4], vec![2]]);
+}
+
+#[tokio::test]
+async fn queues_track_how_long_they_exceed_their_limit() {
+  let queue = OutboundQueue::new(100);
+  queue.push(vec![0; 100], Traffic::Chunk(ivec3(0, 0, 0)));
+  assert!(queue.over_limit_since().is_none());
+
+  queue.push(vec![0; 50], Traffic::Chunk(ivec3(1, 0, 0)));
+  assert!(queue.over_limit_since().is_some());
+
+  queue.next().await;
+  assert!(queue.over_limit_since().is_none());
+}
+
+#[tokio::test]
+async fn queues_far_over_their_limit_are_closed() {
+  let queue = OutboundQueue::new(100);
+  queue.push(vec![1], Traffic::Priority);
+  queue.push(vec![0; 250], Traffic::Chunk(ivec3(0, 0, 0)));
+
+  // everything queued is dropped, the connection is useless by now
+  assert_eq!(queue.next().await, None);
+  queue.push(vec![2], Traffic::Priority);
+  assert_eq!(queue.queued_bytes(), 0);
+}
+
+#[tokio::test]
+async fn waiting_receivers_are_woken_up() {
+  let queue = std::sync::Arc::new(OutboundQueue::new(1024));
+  let receiver = tokio::spawn({
+    let queue = queue.clone();
+    async move { queue.next().await }
+  });
+
+  tokio::task::yield_now().await;
+  queue.push(vec![7], Traffic::Priority);
+  assert_eq!(receiver.await.unwrap(), Some(vec![7]));
+}
+
+#[tokio::test]
+async fn spawns_and_despawns_keep_movement_behind_them() {
+  let uuid = Uuid::new_v4();
+  let queue = OutboundQueue::new(1024);
+  queue.push(vec![1], Traffic::Movement(uuid));
+  queue.push(vec![2], Traffic::Visibility(uuid));
+  queue.push(vec![3], Traffic::Visibility(uuid));
+  queue.push(vec![4, 4], Traffic::Movement(uuid));
+  queue.push(vec![5, 5, 5], Traffic::Movement(uuid));
+  assert_eq!(queue.queued_bytes(), 5);
+
+  assert_eq!(drain(&queue).await, [vec![2], vec![3], vec![5, 5, 5]]);
+}
+
+#[tokio::test]
+async fn unloading_resent_chunks_still_unloads_them() {
+  let queue = OutboundQueue::new(1024);
+  queue.push(vec![1], Traffic::Chunk(ivec3(0, 0, 0)));
+  assert_eq!(queue.next().await, Some(vec![1]));
+
+  // the chunk is resent after the client received it, then leaves the view before the copy goes out
+  queue.push(vec![2; 100], Traffic::Chunk(ivec3(0, 0, 0)));
+  queue.push(vec![3], Traffic::ChunkUnload(ivec3(0, 0, 0)));
+  assert_eq!(queue.queued_bytes(), 1);
+  assert_eq!(drain(&queue).await, [vec![3]]);
+}
+
+#[tokio::test]
+async fn chunks_are_only_unloaded_once() {
+  let queue = OutboundQueue::new(1024);
+  queue.push(vec![1], Traffic::Chunk(ivec3(0, 0, 0)));
+  assert_eq!(queue.next().await, Some(vec![1]));
+
+  queue.push(vec![2], Traffic::ChunkUnload(ivec3(0, 0, 0)));
+  queue.push(vec![3], Traffic::Chunk(ivec3(0, 0, 0)));
+  queue.push(vec![4], Traffic::ChunkUnload(ivec3(0, 0, 0)));
+
+  // the second copy never reached the client, so neither does its unload
+  assert_eq!(drain(&queue).await, [vec![2]]);
+}
\ No newline at end of file
@@ -0,0 +1,163 @@
+use uvxl::game::world::palette::{PalettedContainer, PalettedContainerData};
+
+const LEN: usize = 4096;
+
+type Container = PalettedContainer<u16, LEN>;
+
+fn bits(container: &Container) -> u8 {
+  match container {
+    PalettedContainer::Single(_) => 0,
+    PalettedContainer::Indirect { bits, .. } => *bits,
+  }
+}
+
+fn decode(container: &Container) -> Result<Container, bincode::Error> {
+  bincode::deserialize(&bincode::serialize(container).unwrap())
+}
+
+#[test]
+fn set_and_get_across_widening() {
+  let mut container = Container::new(0);
+  assert_eq!(container.single(), Some(0));
+
+  // Every new value past a power of two widens the indices, which must keep the earlier ones.
+  for value in 1 .. 300u16 {
+    container.set(value as usize * 13, value);
+    for earlier in 1 ..= value {
+      assert_eq!(container.get(earlier as usize * 13), earlier, "lost value {} after inserting {}", earlier, value);
+    }
+  }
+
+  assert_eq!(bits(&container), 9);
+  assert_eq!(container.palette().len(), 300);
+  assert_eq!(container.get(1), 0);
+  assert_eq!(container.get(LEN - 1), 0);
+  assert_eq!(container.iter().filter(|x| *x != 0).count(), 299);
+}
+
+#[test]
+fn set_to_current_single_value_stays_single() {
+  let mut container = Container::new(7);
+  container.set(100, 7);
+  assert_eq!(container.single(), Some(7));
+
+  container.set(100, 8);
+  assert_eq!(container.single(), None);
+  assert_eq!(container.get(100), 8);
+  assert_eq!(container.get(101), 7);
+
+  container.fill(3);
+  assert_eq!(container.single(), Some(3));
+  assert!(container.iter().all(|x| x == 3));
+}
+
+#[test]
+fn optimize_shrinks_the_palette() {
+  let mut container = Container::new(0);
+  for value in 1 .. 20u16 {
+    container.set(value as usize, value);
+  }
+  assert_eq!(bits(&container), 5);
+
+  // Overwrite all but two of the values so most of the palette goes unused.
+  for value in 1 .. 18u16 {
+    container.set(value as usize, 0);
+  }
+
+  let before: Vec<u16> = container.iter().collect();
+  container.optimize();
+
+  assert_eq!(container.palette(), &[0, 18, 19]);
+  assert_eq!(bits(&container), 2);
+  assert!(container.iter().eq(before.into_iter()));
+
+  container.set(18, 0);
+  container.set(19, 0);
+  container.optimize();
+  assert_eq!(container.single(), Some(0));
+}
+
+#[test]
+fn round_trips_through_serde() {
+  let mut container = Container::new(1);
+  for i in 0 .. LEN {
+    container.set(i, (i % 37) as u16);
+  }
+
+  let decoded = decode(&container).unwrap();
+  assert!(decoded.iter().eq(container.iter()));
+  assert_eq!(decode(&Container::new(5)).unwrap().single(), Some(5));
+}
+
+#[test]
+fn rejects_invalid_data() {
+  let words = LEN / 32;
+
+  let wrong_length = PalettedContainerData::Indirect { palette: vec![0, 1, 2], bits: 2, data: vec![0; words - 1] };
+  assert!(Container::try_from(wrong_length).is_err());
+
+  let extra_words = PalettedContainerData::Indirect { palette: vec![0, 1, 2], bits: 2, data: vec![0; words + 1] };
+  assert!(Container::try_from(extra_words).is_err());
+
+  // Index 3 doesn't exist in a palette of three entries.
+  let mut data = vec![0; words];
+  data[words / 2] = 3 << 10;
+  let bad_index = PalettedContainerData::Indirect { palette: vec![0, 1, 2], bits: 2, data };
+  assert!(Container::try_from(bad_index).is_err());
+
+  let too_small = PalettedContainerData::Indirect { palette: vec![0, 1, 2], bits: 1, data: vec![0; LEN / 64] };
+  assert!(Container::try_from(too_small).is_err());
+
+  let empty = PalettedContainerData::Indirect { palette: vec![], bits: 1, data: vec![0; LEN / 64] };
+  assert!(Container::try_from(empty).is_err());
+
+  let zero_bits = PalettedContainerData::Indirect { palette: vec![0], bits: 0, data: vec![] };
+  assert!(Container::try_from(zero_bits).is_err());
+
+  let valid = PalettedContainerData::Indirect { palette: vec![0, 1, 2], bits: 2, data: vec![0; words] };
+  assert!(Container::try_from(valid).is_ok());
+}
+
+#[test]
+fn replaced_values_are_dropped_before_widening() {
+  let mut container = Container::new(0);
+  for value in 1 .. 4u16 {
+    container.set(value as usize, value);
+  }
+  assert_eq!(bits(&container), 2);
+
+  // every value is replaced by a new one, the indices only grow until the replaced values are dropped
+  for value in 4 .. 100u16 {
+    for i in 0 .. LEN {
+      container.set(i, value);
+    }
+
+    assert!(bits(&container) <= 3, "indices widened to {} bits after replacing every value with {}", bits(&container), value);
+    assert!(container.iter().all(|x| x == value));
+  }
+}
+
+#[test]
+fn replacing_every_value_shrinks_the_serialized_indices() {
+  let mut container = Container::new(0);
+  for i in 0 .. LEN {
+    container.set(i, (i % 300) as u16);
+  }
+  assert_eq!(bits(&container), 9);
+  let wide = bincode::serialize(&container).unwrap().len();
+
+  for i in 0 .. LEN {
+    container.set(i, 1000 + (i % 2) as u16);
+  }
+
+  let decoded = decode(&container).unwrap();
+  assert_eq!(bits(&decoded), 1);
+  assert_eq!(decoded.palette(), &[1000, 1001]);
+  assert!(decoded.iter().eq(container.iter()));
+  assert!(bincode::serialize(&container).unwrap().len() * 4 < wide);
+
+  for i in 0 .. LEN {
+    container.set(i, 7);
+  }
+  assert_eq!(decode(&container).unwrap().single(), Some(7));
+}
\ No newline at end of file
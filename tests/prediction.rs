@@ -0,0 +1,67 @@
+use glam::{vec3, Vec3};
+use uvxl::game::entity::prediction::MovementPrediction;
+
+#[test]
+fn moves_are_numbered_and_relative() {
+  let mut prediction = MovementPrediction::new(vec3(1.0, 2.0, 3.0));
+  assert_eq!(prediction.record(vec3(2.0, 2.0, 3.0)), (0, vec3(1.0, 0.0, 0.0)));
+  assert_eq!(prediction.record(vec3(2.0, 5.0, 3.0)), (1, vec3(0.0, 3.0, 0.0)));
+  assert_eq!(prediction.pending(), 2);
+}
+
+#[test]
+fn agreeing_acknowledgements_change_nothing() {
+  let mut prediction = MovementPrediction::new(Vec3::ZERO);
+  prediction.record(vec3(1.0, 0.0, 0.0));
+  prediction.record(vec3(2.0, 0.0, 0.0));
+  prediction.record(vec3(3.0, 0.0, 0.0));
+
+  assert_eq!(prediction.acknowledge(1, vec3(2.0, 0.0, 0.0), vec3(3.5, 0.0, 0.0)), None);
+  assert_eq!(prediction.pending(), 1);
+}
+
+#[test]
+fn corrections_replay_unacknowledged_moves() {
+  let mut prediction = MovementPrediction::new(Vec3::ZERO);
+  prediction.record(vec3(1.0, 0.0, 0.0));
+  prediction.record(vec3(2.0, 0.0, 0.0));
+  prediction.record(vec3(2.0, 1.0, 0.0));
+
+  // the server stopped the first move halfway, the player has moved a bit since the last recorded move
+  let corrected = prediction.acknowledge(0, vec3(0.5, 0.0, 0.0), vec3(2.0, 1.0, 0.25));
+  assert_eq!(corrected, Some(vec3(1.5, 1.0, 0.25)));
+  assert_eq!(prediction.pending(), 2);
+
+  // later moves are relative to the corrected position
+  assert_eq!(prediction.record(vec3(1.5, 1.0, 1.0)), (3, vec3(0.0, 0.0, 1.0)));
+  assert_eq!(prediction.acknowledge(3, vec3(1.5, 1.0, 1.0), vec3(1.5, 1.0, 1.0)), None);
+  assert_eq!(prediction.pending(), 0);
+}
+
+#[test]
+fn stale_acknowledgements_keep_newer_moves() {
+  let mut prediction = MovementPrediction::new(Vec3::ZERO);
+  prediction.record(vec3(1.0, 0.0, 0.0));
+  prediction.record(vec3(2.0, 0.0, 0.0));
+  prediction.acknowledge(1, vec3(2.0, 0.0, 0.0), vec3(2.0, 0.0, 0.0));
+
+  prediction.record(vec3(3.0, 0.0, 0.0));
+  assert_eq!(prediction.acknowledge(0, vec3(1.0, 0.0, 0.0), vec3(3.0, 0.0, 0.0)), None);
+  assert_eq!(prediction.pending(), 1);
+}
+
+#[test]
+fn latest_move_can_be_corrected_again() {
+  let mut prediction = MovementPrediction::new(Vec3::ZERO);
+  prediction.record(vec3(1.0, 0.0, 0.0));
+  prediction.acknowledge(0, vec3(1.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0));
+
+  // teleported without moving since
+  assert_eq!(prediction.acknowledge(0, vec3(10.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0)), Some(vec3(10.0, 0.0, 0.0)));
+
+  // or before the first move
+  let mut prediction = MovementPrediction::new(Vec3::ZERO);
+  prediction.record(vec3(1.0, 0.0, 0.0));
+  assert_eq!(prediction.acknowledge(u32::MAX, vec3(5.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0)), Some(vec3(6.0, 0.0, 0.0)));
+  assert_eq!(prediction.pending(), 1);
+}
\ No newline at end of file
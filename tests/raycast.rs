@@ -0,0 +1,71 @@
+use glam::{ivec3, vec3, IVec3, Vec3};
+use uvxl::game::world::raycast::raycast;
+
+fn block_at(target: IVec3) -> impl FnMut(IVec3) -> bool {
+  move |position| position == target
+}
+
+fn assert_close(a: f32, b: f32) {
+  assert!((a - b).abs() < 1e-4, "{} != {}", a, b);
+}
+
+#[test]
+fn hits_report_the_entered_face() {
+  let origin = vec3(0.5, 0.5, 0.5);
+
+  for (target, normal) in [
+    (ivec3(4, 0, 0), IVec3::NEG_X),
+    (ivec3(-4, 0, 0), IVec3::X),
+    (ivec3(0, 4, 0), IVec3::NEG_Y),
+    (ivec3(0, -4, 0), IVec3::Y),
+    (ivec3(0, 0, 4), IVec3::NEG_Z),
+    (ivec3(0, 0, -4), IVec3::Z),
+  ] {
+    let hit = raycast(origin, target.as_vec3(), 10.0, block_at(target)).unwrap();
+    assert_eq!(hit.position, target);
+    assert_eq!(hit.normal, normal);
+    assert_close(hit.distance, 3.5);
+  }
+}
+
+#[test]
+fn diagonal_rays_enter_through_the_nearest_face() {
+  // steep enough to cross into the block above before reaching the next one along x
+  let hit = raycast(vec3(0.5, 0.5, 0.5), vec3(1.0, 2.0, 0.0), 10.0, block_at(ivec3(0, 1, 0))).unwrap();
+  assert_eq!(hit.position, ivec3(0, 1, 0));
+  assert_eq!(hit.normal, IVec3::NEG_Y);
+  assert_close(hit.distance, Vec3::new(0.25, 0.5, 0.0).length());
+
+  let hit = raycast(vec3(0.5, 0.5, 0.5), vec3(2.0, 1.0, 0.0), 10.0, block_at(ivec3(1, 0, 0))).unwrap();
+  assert_eq!(hit.position, ivec3(1, 0, 0));
+  assert_eq!(hit.normal, IVec3::NEG_X);
+
+  // the ray passes exactly through block corners here, a floor above is still entered from below
+  let hit = raycast(vec3(0.5, 0.5, 0.5), vec3(1.0, 1.0, 0.0), 10.0, |x| x.y >= 3).unwrap();
+  assert_eq!(hit.position.y, 3);
+  assert_eq!(hit.normal, IVec3::NEG_Y);
+}
+
+#[test]
+fn starting_inside_a_block_has_no_normal() {
+  let hit = raycast(vec3(2.3, -1.7, 5.9), Vec3::X, 10.0, |_| true).unwrap();
+  assert_eq!(hit.position, ivec3(2, -2, 5));
+  assert_eq!(hit.normal, IVec3::ZERO);
+  assert_eq!(hit.distance, 0.0);
+}
+
+#[test]
+fn misses_beyond_the_max_distance() {
+  let target = ivec3(5, 0, 0);
+  assert!(raycast(vec3(0.5, 0.5, 0.5), Vec3::X, 4.0, block_at(target)).is_none());
+  assert!(raycast(vec3(0.5, 0.5, 0.5), Vec3::X, 4.5, block_at(target)).is_some());
+  assert!(raycast(vec3(0.5, 0.5, 0.5), Vec3::ZERO, 10.0, |_| true).is_none());
+}
+
+#[test]
+fn negative_coordinates_floor_correctly() {
+  let hit = raycast(vec3(-0.5, 0.5, -0.5), Vec3::NEG_Z, 10.0, block_at(ivec3(-1, 0, -3))).unwrap();
+  assert_eq!(hit.position, ivec3(-1, 0, -3));
+  assert_eq!(hit.normal, IVec3::Z);
+  assert_close(hit.distance, 1.5);
+}
\ No newline at end of file
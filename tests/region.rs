@@ -0,0 +1,129 @@
+#![cfg(feature = "server")]
+
+use std::path::PathBuf;
+use glam::ivec3;
+use uuid::Uuid;
+use uvxl::game::world::BlockId;
+use uvxl::game::world::block_registry::BlockRegistry;
+use uvxl::game::world::chunk::{Chunk, CHUNK_SIZE};
+use uvxl::server::world::region::RegionFile;
+use uvxl::server::world::storage::{WorldMetadata, WorldStorage};
+
+fn temp_path() -> PathBuf {
+  std::env::temp_dir().join(format!("uvxl-{}.region", Uuid::new_v4()))
+}
+
+// Every block differs from its neighbours, so the chunk takes up several sectors
+fn noisy_chunk(seed: usize) -> Chunk {
+  let blocks = BlockRegistry::builtin().iter().map(|(id, _)| id).collect::<Vec<_>>();
+  let mut chunk = Chunk::default();
+  for x in 0 .. CHUNK_SIZE {
+    for y in 0 .. CHUNK_SIZE {
+      for z in 0 .. CHUNK_SIZE {
+        chunk.set_block(x, y, z, blocks[(x * 7 + y * 3 + z + seed) % blocks.len()]);
+      }
+    }
+  }
+
+  chunk
+}
+
+fn blocks(chunk: &Chunk) -> Vec<BlockId> {
+  chunk.blocks.iter().collect()
+}
+
+#[test]
+fn chunks_survive_reopening() {
+  let path = temp_path();
+  let chunk = noisy_chunk(0);
+
+  let mut region = RegionFile::open(&path).unwrap();
+  region.write_chunk(ivec3(1, 2, 3), &chunk).unwrap();
+  assert_eq!(region.read_chunk(ivec3(1, 2, 3)).unwrap().map(|x| blocks(&x)), Some(blocks(&chunk)));
+  assert!(region.read_chunk(ivec3(3, 2, 1)).unwrap().is_none());
+  drop(region);
+
+  let mut region = RegionFile::open(&path).unwrap();
+  assert_eq!(region.read_chunk(ivec3(1, 2, 3)).unwrap().map(|x| blocks(&x)), Some(blocks(&chunk)));
+  assert!(region.read_chunk(ivec3(3, 2, 1)).unwrap().is_none());
+
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn growing_chunks_move_without_overwriting_others() {
+  let path = temp_path();
+  let (small, large, other) = (Chunk::default(), noisy_chunk(1), noisy_chunk(2));
+
+  // the large chunk no longer fits where the small one was, the other chunk follows right after it
+  let mut region = RegionFile::open(&path).unwrap();
+  region.write_chunk(ivec3(0, 0, 0), &small).unwrap();
+  region.write_chunk(ivec3(0, 0, 1), &other).unwrap();
+  region.write_chunk(ivec3(0, 0, 0), &large).unwrap();
+  drop(region);
+
+  let mut region = RegionFile::open(&path).unwrap();
+  assert_eq!(region.read_chunk(ivec3(0, 0, 0)).unwrap().map(|x| blocks(&x)), Some(blocks(&large)));
+  assert_eq!(region.read_chunk(ivec3(0, 0, 1)).unwrap().map(|x| blocks(&x)), Some(blocks(&other)));
+
+  // shrinking it again reuses the free space instead of growing the file
+  let length = std::fs::metadata(&path).unwrap().len();
+  region.write_chunk(ivec3(0, 0, 0), &small).unwrap();
+  region.write_chunk(ivec3(0, 0, 0), &large).unwrap();
+  assert_eq!(std::fs::metadata(&path).unwrap().len(), length);
+
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rewriting_chunks_keeps_the_previous_copy() {
+  let path = temp_path();
+  let mut region = RegionFile::open(&path).unwrap();
+  region.write_chunk(ivec3(1, 2, 3), &noisy_chunk(1)).unwrap();
+  let before = std::fs::read(&path).unwrap();
+
+  // only the chunk's header entry may change, a crash while writing the new copy must not damage the old one
+  region.write_chunk(ivec3(1, 2, 3), &noisy_chunk(2)).unwrap();
+  let after = std::fs::read(&path).unwrap();
+  let changed = (0 .. before.len()).filter(|i| before[*i] != after[*i]).collect::<Vec<_>>();
+  assert!(after.len() > before.len());
+  assert!(changed.last().unwrap() - changed[0] < 8, "bytes {:?} changed", changed);
+
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn corrupt_files_are_refused() {
+  let path = temp_path();
+  let mut region = RegionFile::open(&path).unwrap();
+  region.write_chunk(ivec3(0, 0, 0), &noisy_chunk(0)).unwrap();
+  drop(region);
+
+  // the header points past the end of the file
+  let length = std::fs::metadata(&path).unwrap().len();
+  std::fs::OpenOptions::new().write(true).open(&path).unwrap().set_len(length - 1).unwrap();
+  assert!(RegionFile::open(&path).is_err());
+
+  // not a region file, or one with an incomplete header
+  std::fs::write(&path, vec![0xff; length as usize]).unwrap();
+  assert!(RegionFile::open(&path).is_err());
+  std::fs::write(&path, b"UVXR").unwrap();
+  assert!(RegionFile::open(&path).is_err());
+
+  std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn world_metadata_survives_reopening() {
+  let directory = std::env::temp_dir().join(format!("uvxl-{}", Uuid::new_v4()));
+
+  let storage = WorldStorage::new(&directory).unwrap();
+  assert!(storage.load_metadata().unwrap().is_none());
+  storage.save_metadata(&WorldMetadata { seed: 1337 }).unwrap();
+  drop(storage);
+
+  let storage = WorldStorage::new(&directory).unwrap();
+  assert_eq!(storage.load_metadata().unwrap().map(|x| x.seed), Some(1337));
+
+  std::fs::remove_dir_all(&directory).unwrap();
+}
\ No newline at end of file
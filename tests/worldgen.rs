@@ -0,0 +1,191 @@
+use std::collections::{HashMap, HashSet};
+use glam::{ivec3, IVec3};
+use uvxl::game::world::BlockId;
+use uvxl::game::world::biome::BiomeId;
+use uvxl::game::world::block_registry::BlockRegistry;
+use uvxl::game::world::chunk::CHUNK_SIZE;
+use uvxl::game::world::worldgen::caves::CaveCarver;
+use uvxl::game::world::worldgen::noise::GradientNoise;
+use uvxl::game::world::worldgen::worldgen::WorldGen;
+
+#[test]
+fn same_seed_generates_identical_chunks() {
+  let registry = BlockRegistry::builtin();
+  let a = WorldGen::new(&registry, 1337);
+  let b = WorldGen::new(&registry, 1337);
+
+  for chunk_pos in [ivec3(0, 0, 0), ivec3(-1, 0, -1), ivec3(3, 1, -7), ivec3(-20, -1, 15), ivec3(100_000, 0, -100_000)] {
+    let a = bincode::serialize(&a.generate(chunk_pos)).unwrap();
+    let b = bincode::serialize(&b.generate(chunk_pos)).unwrap();
+    assert_eq!(a, b, "chunk {} differs", chunk_pos);
+  }
+}
+
+#[test]
+fn different_seeds_generate_different_terrain() {
+  let registry = BlockRegistry::builtin();
+  let a = WorldGen::new(&registry, 1);
+  let b = WorldGen::new(&registry, 2);
+
+  let heights = |worldgen: &WorldGen| (-64 .. 64).map(|x| worldgen.height(x * 7, x * 3)).collect::<Vec<_>>();
+  assert_ne!(heights(&a), heights(&b));
+}
+
+#[test]
+fn terrain_is_continuous_across_negative_coordinates() {
+  let registry = BlockRegistry::builtin();
+  let worldgen = WorldGen::new(&registry, 42);
+
+  for x in -40 .. 40 {
+    let difference = (worldgen.height(x, -3) - worldgen.height(x + 1, -3)).abs();
+    assert!(difference <= 2, "height jumps by {} between x = {} and x = {}", difference, x, x + 1);
+  }
+}
+
+#[test]
+fn chunks_store_the_biome_of_every_column() {
+  let registry = BlockRegistry::builtin();
+  let worldgen = WorldGen::new(&registry, 7);
+
+  let chunk = worldgen.generate(ivec3(-3, 0, 5));
+  for x in 0 .. CHUNK_SIZE {
+    for z in 0 .. CHUNK_SIZE {
+      let climate = worldgen.climate(-3 * CHUNK_SIZE as i32 + x as i32, 5 * CHUNK_SIZE as i32 + z as i32);
+      assert_eq!(chunk.get_biome(x, z), climate.biome);
+    }
+  }
+}
+
+#[test]
+fn biome_borders_are_blended() {
+  let registry = BlockRegistry::builtin();
+  let worldgen = WorldGen::new(&registry, 7);
+
+  // walk far enough to cross several biomes, the terrain must not form cliffs at their borders
+  let mut biomes = HashSet::new();
+  for x in -4096 .. 4096 {
+    biomes.insert(worldgen.climate(x, 100).biome);
+
+    let difference = (worldgen.height(x, 100) - worldgen.height(x + 1, 100)).abs();
+    assert!(difference <= 3, "height jumps by {} between x = {} and x = {}", difference, x, x + 1);
+  }
+
+  assert!(biomes.len() >= 2, "the path only crosses {:?}", biomes);
+}
+
+#[test]
+fn terrain_does_not_repeat() {
+  let noise = GradientNoise::new(7);
+  let repeated = (0 .. 1000).filter(|i| {
+    let (x, y) = (*i as f64 * 0.37, *i as f64 * 0.11);
+    noise.sample_2d(x, y) == noise.sample_2d(x + 256.0, y)
+  });
+  assert!(repeated.count() < 100, "noise repeats every 256 lattice cells");
+
+  // a permutation table would repeat the height map within the world border
+  let registry = BlockRegistry::builtin();
+  let worldgen = WorldGen::new(&registry, 7);
+  let same = (0 .. 256).filter(|x| worldgen.height(x * 16, 100) == worldgen.height(x * 16 + 65536, 100)).count();
+  assert!(same < 128, "{} of 256 columns repeat 65536 blocks away", same);
+}
+
+
+#[test]
+fn caves_are_carved_underground() {
+  let registry = BlockRegistry::builtin();
+  let worldgen = WorldGen::new(&registry, 7);
+
+  let mut carved = 0;
+  for chunk_pos in [ivec3(0, -1, 0), ivec3(1, -2, 0), ivec3(-1, -1, 2), ivec3(0, -3, -1)] {
+    let chunk = worldgen.generate(chunk_pos);
+    carved += (0 .. CHUNK_SIZE).flat_map(|x| (0 .. CHUNK_SIZE).flat_map(move |y| (0 .. CHUNK_SIZE).map(move |z| (x, y, z))))
+      .filter(|(x, y, z)| chunk.get_block(*x, *y, *z) == BlockId::AIR)
+      .count();
+  }
+
+  assert!(carved > 0, "no caves below the surface");
+}
+
+#[test]
+fn worm_caves_continue_across_chunk_borders() {
+  let caves = CaveCarver::new(7);
+  let size = CHUNK_SIZE as i32;
+
+  let carve = |min: IVec3, max: IVec3| {
+    let mut blocks = HashSet::new();
+    caves.carve_worms(min, max, |x| { blocks.insert(x); });
+    blocks
+  };
+
+  // carving a 2x1x2 area of chunks at once must give the same result as carving every chunk on its own
+  let origin = ivec3(-1, -2, -1) * size;
+  let whole = carve(origin, origin + ivec3(2, 1, 2) * size);
+  let mut parts = HashSet::new();
+  for offset in [ivec3(0, 0, 0), ivec3(1, 0, 0), ivec3(0, 0, 1), ivec3(1, 0, 1)] {
+    let min = origin + offset * size;
+    parts.extend(carve(min, min + size));
+  }
+
+  assert!(!whole.is_empty(), "no worms reach the area");
+  assert_eq!(whole, parts);
+}
+
+#[test]
+fn ore_veins_are_placed_in_stone() {
+  let registry = BlockRegistry::builtin();
+  let worldgen = WorldGen::new(&registry, 7);
+  let coal = registry.by_name("coal_ore").unwrap();
+
+  let chunk = worldgen.generate(ivec3(0, -2, 0));
+  assert!(chunk.blocks.iter().any(|x| x == coal), "no coal below the surface");
+}
+
+#[test]
+fn trees_straddling_chunk_borders_are_complete() {
+  let registry = BlockRegistry::builtin();
+  let worldgen = WorldGen::new(&registry, 7);
+  let (log, leaves) = (registry.by_name("log").unwrap(), registry.by_name("leaves").unwrap());
+  let size = CHUNK_SIZE as i32;
+
+  // a forest column of chunks, so there are plenty of trees
+  let center = (-64 .. 64).flat_map(|x| (-64 .. 64).map(move |z| ivec3(x, 0, z)))
+    .find(|x| worldgen.climate(x.x * size + size / 2, x.z * size + size / 2).biome == BiomeId::FOREST)
+    .expect("no forest nearby");
+
+  let surface = worldgen.height(center.x * size + size / 2, center.z * size + size / 2);
+  let center = ivec3(center.x, surface.div_euclid(size), center.z);
+
+  let mut blocks = HashMap::new();
+  for x in -1 ..= 1 {
+    for y in -1 ..= 1 {
+      for z in -1 ..= 1 {
+        let chunk_pos = center + ivec3(x, y, z);
+        let chunk = worldgen.generate(chunk_pos);
+        for (i, block) in chunk.blocks.iter().enumerate() {
+          let local = ivec3(i as i32 % size, i as i32 / size % size, i as i32 / (size * size));
+          blocks.insert(chunk_pos * size + local, block);
+        }
+      }
+    }
+  }
+
+  // the crown of every tree covers the top of its trunk from above and all sides, unless another trunk is in the way
+  let mut across_borders = 0;
+  for (position, block) in &blocks {
+    let above = *position + IVec3::Y;
+    if *block != log || blocks.get(&above) == Some(&log) {
+      continue;
+    }
+
+    for neighbour in [above, above + IVec3::X, above - IVec3::X, above + IVec3::Z, above - IVec3::Z] {
+      let Some(block) = blocks.get(&neighbour) else { continue };
+      assert!(*block == leaves || *block == log, "tree at {} is missing leaves at {}", position, neighbour);
+
+      if neighbour.div_euclid(IVec3::splat(size)) != position.div_euclid(IVec3::splat(size)) {
+        across_borders += 1;
+      }
+    }
+  }
+
+  assert!(across_borders > 0, "no tree crosses a chunk border");
+}
\ No newline at end of file
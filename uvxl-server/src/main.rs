@@ -1,9 +1,28 @@
+use uvxl::server::command::CommandSource;
 use uvxl::server::server::Server;
+use uvxl::server::server_settings::ServerSettings;
 
+use std::io::BufRead;
 use std::net::SocketAddr;
 
 fn main() {
   pretty_env_logger::init();
-  let server = Box::leak(Box::new(Server::new()));
+  let settings = ServerSettings::load("settings.json").unwrap();
+  let server: &'static Server = Box::leak(Box::new(Server::new(settings).unwrap()));
+
+  // lines typed into the terminal run as commands of the console, the slash is optional.
+  // Without a terminal, e.g. as a service, stdin ends right away and the server keeps running
+  std::thread::Builder::new()
+    .name(String::from("console"))
+    .spawn(move || {
+      for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+        let line = line.trim();
+        if !line.is_empty() {
+          server.execute(CommandSource::Console, line.strip_prefix('/').unwrap_or(line));
+        }
+      }
+    })
+    .unwrap();
+
   server.run(SocketAddr::from(([0, 0, 0, 0], 2488))).unwrap();
-}
+}
\ No newline at end of file